@@ -1,16 +1,52 @@
-use std::path::PathBuf;
+use std::{
+    net::IpAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use clap::{Args, Parser};
-use katana_core::{constants::DEFAULT_GAS_PRICE, starknet::StarknetConfig};
+use clap::{Args, Parser, Subcommand};
+use katana_core::{
+    constants::{DEFAULT_GAS_PRICE, FEE_TOKEN_ADDRESS, UDC_ADDRESS},
+    starknet::{policy::DeclarePolicy, StarknetConfig},
+};
 use katana_rpc::config::RpcConfig;
+use starknet_api::{
+    core::{ContractAddress, PatriciaKey},
+    hash::{StarkFelt, StarkHash},
+    patricia_key, StarknetApiError,
+};
 
 #[derive(Parser, Debug)]
 #[command(about = "A fast and lightweight local Starknet development node.")]
+#[command(disable_version_flag = true)]
 pub struct App {
+    #[arg(short = 'V', long)]
+    #[arg(help = "Print version.")]
+    pub version: bool,
+
+    #[arg(long)]
+    #[arg(requires = "version")]
+    #[arg(help = "Print the version information as JSON.")]
+    #[arg(
+        long_help = "Print the version information as JSON, along with the capabilities of the node (compiled-in features, supported JSON-RPC specification version and database schema version), so that it can be read by other tools."
+    )]
+    pub json: bool,
+
     #[arg(long)]
     #[arg(help = "Hide the predeployed accounts details.")]
     pub hide_predeployed_accounts: bool,
 
+    #[arg(long)]
+    #[arg(value_name = "PATH")]
+    #[arg(help = "Write the startup information to a JSON file.")]
+    #[arg(
+        long_help = "Write the startup information (RPC address, chain ID, fee token and predeployed accounts) to a JSON file once the node is ready, so that it can be read by other tools."
+    )]
+    pub startup_info_out: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     #[command(flatten)]
     #[command(next_help_heading = "Server options")]
     pub rpc: RpcOptions,
@@ -20,12 +56,67 @@ pub struct App {
     pub starknet: StarknetOptions,
 }
 
+#[derive(Debug, Subcommand, Clone)]
+pub enum Command {
+    #[command(about = "Start the node after replaying a recording made with `--record`.")]
+    #[command(
+        long_about = "Start the node after re-executing the calls modifying the chain from a recording made with `--record`. The recorded transactions are replayed as they were signed, so the node should be started with the same options as the recorded one."
+    )]
+    Replay {
+        #[arg(value_name = "PATH")]
+        #[arg(help = "The recording to replay.")]
+        path: PathBuf,
+    },
+}
+
 #[derive(Debug, Args, Clone)]
 pub struct RpcOptions {
     #[arg(short, long)]
     #[arg(default_value = "5050")]
     #[arg(help = "Port number to listen on.")]
     pub port: u16,
+
+    #[arg(long = "host")]
+    #[arg(value_name = "ADDR")]
+    #[arg(default_value = "127.0.0.1")]
+    #[arg(help = "IP address to listen on. Can be specified multiple times.")]
+    pub hosts: Vec<IpAddr>,
+
+    #[arg(long)]
+    #[arg(value_name = "PATH")]
+    #[arg(help = "Record the write methods invoked on the server to a JSON lines file.")]
+    pub audit_log: Option<PathBuf>,
+
+    #[arg(long)]
+    #[arg(value_name = "MILLISECONDS")]
+    #[arg(help = "Log the calls taking longer than the given duration.")]
+    pub slow_call_threshold: Option<u64>,
+
+    #[arg(long)]
+    #[arg(value_name = "PORT")]
+    #[arg(help = "Stream new block headers and events as server-sent events on the given port.")]
+    #[arg(
+        long_help = "Stream new block headers and events as server-sent events on the given port, for clients which can't use WebSocket subscriptions. Headers are served at `/heads` and events at `/events`, optionally filtered with `from_address` and `keys` query parameters."
+    )]
+    pub sse_port: Option<u16>,
+
+    #[arg(long)]
+    #[arg(value_name = "SECONDS")]
+    #[arg(default_value = "15")]
+    #[arg(value_parser = clap::value_parser!(u64).range(1..))]
+    #[arg(help = "Interval at which the idle connections are kept alive.")]
+    #[arg(
+        long_help = "Interval at which the WebSocket connections are pinged and a comment is sent on the idle server-sent events streams, so that load balancers and proxies with an idle timeout don't drop long-lived connections."
+    )]
+    pub keep_alive_interval: u64,
+
+    #[arg(long = "record")]
+    #[arg(value_name = "DIR")]
+    #[arg(help = "Record the calls served over HTTP to a JSON lines file in the given directory.")]
+    #[arg(
+        long_help = "Record every call served over HTTP, along with its response, to a JSON lines file in the given directory. The calls modifying the chain can then be re-executed with `katana replay`."
+    )]
+    pub record_dir: Option<PathBuf>,
 }
 
 #[derive(Debug, Args, Clone)]
@@ -48,6 +139,14 @@ pub struct StarknetOptions {
     )]
     pub account_path: Option<PathBuf>,
 
+    #[arg(long)]
+    #[arg(value_name = "PATH")]
+    #[arg(help = "Fee token balances to allocate at genesis.")]
+    #[arg(
+        long_help = "Fee token balances to allocate at genesis; should be a path to either a CSV file of `address,balance` lines or a JSON array of `{ \"address\", \"balance\" }` objects. The predeployed accounts can't be given an allocation."
+    )]
+    pub genesis_allocations: Option<PathBuf>,
+
     #[arg(long)]
     #[arg(help = "Block generation on demand via an endpoint.")]
     pub blocks_on_demand: bool,
@@ -56,6 +155,35 @@ pub struct StarknetOptions {
     #[arg(help = "Allow transaction max fee to be zero.")]
     pub allow_zero_max_fee: bool,
 
+    #[arg(long)]
+    #[arg(help = "Produce identical blocks and hashes across runs.")]
+    #[arg(
+        long_help = "Produce identical blocks and hashes across runs, for snapshot-based test suites. Block timestamps follow a fixed schedule instead of the system time, and a seed cannot be specified."
+    )]
+    #[arg(conflicts_with = "seed")]
+    pub deterministic: bool,
+
+    #[arg(long)]
+    #[arg(help = "Trace the resources used by each entrypoint call.")]
+    #[arg(
+        long_help = "Trace the resources used by each entrypoint call (class hash, selector, steps, memory holes and builtins). They are logged at the debug level under the `executor::entrypoint` target, and accumulated per entrypoint for `dev_getEntrypointStats`."
+    )]
+    pub trace_entrypoints: bool,
+
+    #[arg(long)]
+    #[arg(value_name = "PATH")]
+    #[arg(value_parser = parse_declare_allowlist)]
+    #[arg(help = "Only allow the classes listed in a file to be declared.")]
+    #[arg(
+        long_help = "Only allow the classes listed in a file to be declared; should be a path to a file with one class hash per line. The policy can be changed at runtime with `katana_setDeclarePolicy`."
+    )]
+    pub declare_allowlist: Option<DeclarePolicy>,
+
+    #[arg(long)]
+    #[arg(help = "Reject every class declaration.")]
+    #[arg(conflicts_with = "declare_allowlist")]
+    pub disable_declare: bool,
+
     #[command(flatten)]
     #[command(next_help_heading = "Environment options")]
     pub environment: EnvironmentOptions,
@@ -71,12 +199,31 @@ pub struct EnvironmentOptions {
     #[arg(long)]
     #[arg(help = "The gas price.")]
     pub gas_price: Option<u128>,
+
+    #[arg(long)]
+    #[arg(value_name = "ADDRESS")]
+    #[arg(value_parser = parse_contract_address)]
+    #[arg(help = "The address where the fee token is deployed at genesis.")]
+    pub fee_token_address: Option<ContractAddress>,
+
+    #[arg(long = "udc-address")]
+    #[arg(value_name = "ADDRESS")]
+    #[arg(value_parser = parse_contract_address)]
+    #[arg(help = "The address where the Universal Deployer is deployed at genesis.")]
+    pub universal_deployer_address: Option<ContractAddress>,
 }
 
 impl App {
     pub fn rpc_config(&self) -> RpcConfig {
         RpcConfig {
             port: self.rpc.port,
+            hosts: self.rpc.hosts.clone(),
+            audit_log: self.rpc.audit_log.clone(),
+            slow_call_threshold: self.rpc.slow_call_threshold.map(Duration::from_millis),
+            log_filter: None,
+            sse_port: self.rpc.sse_port,
+            keep_alive_interval: Duration::from_secs(self.rpc.keep_alive_interval),
+            record_dir: self.rpc.record_dir.clone(),
         }
     }
 
@@ -92,11 +239,39 @@ impl App {
             blocks_on_demand: self.starknet.blocks_on_demand,
             account_path: self.starknet.account_path.clone(),
             allow_zero_max_fee: self.starknet.allow_zero_max_fee,
+            deterministic: self.starknet.deterministic,
+            genesis_allocations: self.starknet.genesis_allocations.clone(),
+            trace_entrypoints: self.starknet.trace_entrypoints,
+            declare_policy: if self.starknet.disable_declare {
+                DeclarePolicy::DenyAll
+            } else {
+                self.starknet.declare_allowlist.clone().unwrap_or_default()
+            },
             chain_id: self.starknet.environment.chain_id.clone(),
+            fee_token_address: self
+                .starknet
+                .environment
+                .fee_token_address
+                .unwrap_or(ContractAddress(patricia_key!(*FEE_TOKEN_ADDRESS))),
+            universal_deployer_address: self
+                .starknet
+                .environment
+                .universal_deployer_address
+                .unwrap_or(ContractAddress(patricia_key!(*UDC_ADDRESS))),
         }
     }
 }
 
+fn parse_contract_address(value: &str) -> Result<ContractAddress, StarknetApiError> {
+    Ok(ContractAddress(PatriciaKey::try_from(
+        StarkFelt::try_from(value)?,
+    )?))
+}
+
+fn parse_declare_allowlist(value: &str) -> Result<DeclarePolicy, String> {
+    DeclarePolicy::allowlist_from_file(Path::new(value)).map_err(|err| format!("{err:#}"))
+}
+
 fn parse_seed(seed: Option<String>) -> [u8; 32] {
     seed.map(|seed| {
         let seed = seed.as_bytes();
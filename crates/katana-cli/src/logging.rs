@@ -0,0 +1,168 @@
+use std::{
+    env,
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
+
+use env_logger::filter::{Builder as FilterBuilder, Filter};
+use katana_rpc::config::LogFilterReloader;
+use log::{info, LevelFilter, Log, Metadata, Record};
+
+/// The log filter used when `RUST_LOG` isn't set.
+const DEFAULT_LOG_FILTER: &str = "info";
+
+/// A handle to the filter of the logger installed by [`init`].
+#[derive(Debug, Clone)]
+pub struct LogFilterHandle {
+    filter: Arc<RwLock<Filter>>,
+    /// The directives the node was started with.
+    initial_directives: String,
+}
+
+impl LogFilterHandle {
+    /// Restores the filter the node was started with.
+    pub fn reset(&self) {
+        self.set(&self.initial_directives);
+    }
+
+    fn set(&self, directives: &str) {
+        let filter = FilterBuilder::new().parse(directives).build();
+        log::set_max_level(filter.filter());
+        *self.filter.write().unwrap() = filter;
+    }
+}
+
+impl LogFilterReloader for LogFilterHandle {
+    fn reload(&self, directives: &str) -> Result<(), String> {
+        validate_directives(directives)?;
+        self.set(directives);
+        info!("Log filter set to '{directives}'");
+        Ok(())
+    }
+}
+
+/// Formats the records with `env_logger`, after checking them against a filter which can be
+/// replaced at runtime.
+struct ReloadableLogger {
+    filter: Arc<RwLock<Filter>>,
+    inner: env_logger::Logger,
+}
+
+impl Log for ReloadableLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.filter.read().unwrap().enabled(metadata)
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if self.filter.read().unwrap().matches(record) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Installs the global logger, filtered by `RUST_LOG`, and returns a handle to replace its filter.
+pub fn init() -> LogFilterHandle {
+    let initial_directives =
+        env::var("RUST_LOG").unwrap_or_else(|_| DEFAULT_LOG_FILTER.to_string());
+    let filter = FilterBuilder::new().parse(&initial_directives).build();
+    let max_level = filter.filter();
+
+    // The records are filtered beforehand, so the inner logger lets everything through.
+    let mut inner = env_logger::Builder::new();
+    inner.filter_level(LevelFilter::Trace);
+    if let Ok(write_style) = env::var("RUST_LOG_STYLE") {
+        inner.parse_write_style(&write_style);
+    }
+
+    let filter = Arc::new(RwLock::new(filter));
+
+    log::set_boxed_logger(Box::new(ReloadableLogger {
+        filter: filter.clone(),
+        inner: inner.build(),
+    }))
+    .expect("logger must only be initialized once");
+    log::set_max_level(max_level);
+
+    LogFilterHandle {
+        filter,
+        initial_directives,
+    }
+}
+
+/// Resets the log filter to the one the node was started with whenever the process receives
+/// `SIGHUP`, undoing the changes made through `dev_setLogFilter`.
+#[cfg(unix)]
+pub fn reset_on_sighup(handle: LogFilterHandle) -> std::io::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = signal(SignalKind::hangup())?;
+
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            handle.reset();
+            info!(
+                "Received SIGHUP, log filter reset to '{}'",
+                handle.initial_directives
+            );
+        }
+    });
+
+    Ok(())
+}
+
+// `env_logger` ignores the invalid directives instead of failing, so they are checked
+// beforehand to report them to the caller.
+fn validate_directives(directives: &str) -> Result<(), String> {
+    // Anything after a `/` is a regex matched against the messages.
+    let (directives, _) = directives.split_once('/').unwrap_or((directives, ""));
+
+    for directive in directives.split(',').map(str::trim) {
+        if directive.is_empty() {
+            continue;
+        }
+
+        let mut parts = directive.split('=');
+        let (Some(target), level, None) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(format!("invalid directive '{directive}'"));
+        };
+
+        if let Some(level) = level {
+            if target.is_empty() {
+                return Err(format!("missing target in '{directive}'"));
+            }
+            LevelFilter::from_str(level)
+                .map_err(|_| format!("invalid level '{level}' in '{directive}'"))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_directives() {
+        for directives in [
+            "",
+            "info",
+            "katana_core=trace,info",
+            " katana_rpc=debug , warn ",
+            "katana_core",
+            "info/transaction",
+            "katana_core=debug/a=b=c",
+        ] {
+            assert_eq!(validate_directives(directives), Ok(()), "{directives}");
+        }
+
+        assert!(validate_directives("a=b=c").is_err());
+        assert!(validate_directives("=info").is_err());
+        assert!(validate_directives("katana_core=loud").is_err());
+        assert!(validate_directives("info,katana_core=loud/regex").is_err());
+    }
+}
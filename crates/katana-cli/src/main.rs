@@ -1,26 +1,45 @@
-use std::{process::exit, sync::Arc};
+use std::{fs, net::SocketAddr, path::Path, process::exit, sync::Arc};
 
 use clap::Parser;
-use env_logger::Env;
 use katana_core::sequencer::KatanaSequencer;
-use katana_rpc::KatanaNodeRpc;
-use log::error;
+use katana_rpc::{KatanaNodeRpc, RPC_SPEC_VERSION};
+use log::{error, info};
+use serde_json::json;
 use tokio::sync::RwLock;
 use yansi::Paint;
 
 mod cli;
+mod logging;
 
-use cli::App;
+use cli::{App, Command};
 
 #[tokio::main]
 async fn main() {
-    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+    let log_filter = logging::init();
 
     let config = App::parse();
-    let rpc_config = config.rpc_config();
+
+    if config.version {
+        print_version(config.json);
+        return;
+    }
+
+    #[cfg(unix)]
+    if let Err(err) = logging::reset_on_sighup(log_filter.clone()) {
+        error!("failed to listen for SIGHUP: {err}");
+    }
+
+    let mut rpc_config = config.rpc_config();
+    rpc_config.log_filter = Some(Arc::new(log_filter));
     let starknet_config = config.starknet_config();
 
-    let sequencer = Arc::new(RwLock::new(KatanaSequencer::new(starknet_config)));
+    let sequencer = match KatanaSequencer::new(starknet_config) {
+        Ok(sequencer) => Arc::new(RwLock::new(sequencer)),
+        Err(err) => {
+            error!("failed to create the genesis state: {err:#}");
+            exit(1);
+        }
+    };
     sequencer.write().await.start();
 
     let predeployed_accounts = if config.hide_predeployed_accounts {
@@ -36,21 +55,47 @@ async fn main() {
         )
     };
 
-    match KatanaNodeRpc::new(sequencer.clone(), rpc_config)
-        .run()
-        .await
-    {
-        Ok((addr, server_handle)) => {
+    let node_rpc = KatanaNodeRpc::new(sequencer.clone(), rpc_config);
+
+    if let Some(Command::Replay { path }) = &config.command {
+        match node_rpc.replay(path).await {
+            Ok(calls) => info!("Replayed {calls} calls from {}", path.display()),
+            Err(err) => {
+                error!("failed to replay {}: {err}", path.display());
+                exit(1);
+            }
+        }
+    }
+
+    match node_rpc.run().await {
+        Ok(servers) => {
+            let addrs = servers.iter().map(|(addr, _)| *addr).collect::<Vec<_>>();
+
+            if let Some(path) = &config.startup_info_out {
+                if let Err(err) = write_startup_info(path, &*sequencer.read().await, &addrs) {
+                    error!("failed to write startup info to {}: {err}", path.display());
+                    exit(1);
+                }
+            }
+
             print_intro(
                 predeployed_accounts,
                 config.starknet.seed,
-                format!(
-                    "🚀 JSON-RPC server started: {}",
-                    Paint::red(format!("http://{addr}"))
-                ),
+                addrs
+                    .iter()
+                    .map(|addr| {
+                        format!(
+                            "🚀 JSON-RPC server started: {}",
+                            Paint::red(format!("http://{addr}"))
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
             );
 
-            server_handle.stopped().await;
+            for (_, server_handle) in servers {
+                server_handle.stopped().await;
+            }
         }
         Err(err) => {
             error! {"{}", err};
@@ -59,6 +104,26 @@ async fn main() {
     };
 }
 
+fn print_version(json: bool) {
+    if !json {
+        println!("katana {}", env!("CARGO_PKG_VERSION"));
+        return;
+    }
+
+    // This build has no optional features and keeps its state in memory, hence no database.
+    let report = json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "rpc_spec_version": RPC_SPEC_VERSION,
+        "features": Vec::<String>::new(),
+        "db_schema_version": null,
+    });
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report).expect("valid json")
+    );
+}
+
 fn print_intro(accounts: Option<String>, seed: Option<String>, address: String) {
     println!(
         "{}",
@@ -99,3 +164,34 @@ ACCOUNTS SEED
 
     println!("\n{address}\n\n");
 }
+
+fn write_startup_info(
+    path: &Path,
+    sequencer: &KatanaSequencer,
+    addrs: &[SocketAddr],
+) -> std::io::Result<()> {
+    let accounts = sequencer
+        .starknet
+        .predeployed_accounts
+        .accounts
+        .iter()
+        .map(|account| {
+            json!({
+                "address": account.account_address.0.key().to_string(),
+                "class_hash": account.class_hash.0.to_string(),
+                "private_key": account.private_key.to_string(),
+                "public_key": account.public_key.to_string(),
+                "balance": account.balance.to_string(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let info = json!({
+        "rpc_addresses": addrs.iter().map(|addr| format!("http://{addr}")).collect::<Vec<_>>(),
+        "chain_id": sequencer.starknet.block_context.chain_id.0,
+        "fee_token_address": sequencer.starknet.block_context.fee_token_address.0.key().to_string(),
+        "accounts": accounts,
+    });
+
+    fs::write(path, serde_json::to_string_pretty(&info)?)
+}
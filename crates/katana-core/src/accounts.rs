@@ -15,7 +15,7 @@ use starknet_api::{
 };
 
 use crate::{
-    constants::{DEFAULT_ACCOUNT_CONTRACT, DEFAULT_ACCOUNT_CONTRACT_CLASS_HASH, FEE_TOKEN_ADDRESS},
+    constants::{DEFAULT_ACCOUNT_CONTRACT, DEFAULT_ACCOUNT_CONTRACT_CLASS_HASH},
     state::DictStateReader,
     util::compute_legacy_class_hash,
 };
@@ -56,7 +56,7 @@ impl Account {
         }
     }
 
-    pub fn deploy(&self, state: &mut DictStateReader) {
+    pub fn deploy(&self, state: &mut DictStateReader, fee_token_address: ContractAddress) {
         self.declare(state);
 
         // set the contract
@@ -66,7 +66,7 @@ impl Account {
         // set the balance in the FEE CONTRACT
         state.storage_view.insert(
             (
-                ContractAddress(patricia_key!(*FEE_TOKEN_ADDRESS)),
+                fee_token_address,
                 get_storage_var_address("ERC20_balances", &[*self.account_address.0.key()])
                     .unwrap(),
             ),
@@ -132,9 +132,9 @@ impl PredeployedAccounts {
         })
     }
 
-    pub fn deploy_accounts(&self, state: &mut DictStateReader) {
+    pub fn deploy_accounts(&self, state: &mut DictStateReader, fee_token_address: ContractAddress) {
         for account in &self.accounts {
-            account.deploy(state);
+            account.deploy(state, fee_token_address);
         }
     }
 
@@ -48,7 +48,7 @@ pub fn block_context_from_config(config: &StarknetConfig) -> BlockContext {
         chain_id: ChainId(config.chain_id.clone()),
         block_timestamp: BlockTimestamp::default(),
         sequencer_address: ContractAddress(patricia_key!(*SEQUENCER_ADDRESS)),
-        fee_token_address: ContractAddress(patricia_key!(*FEE_TOKEN_ADDRESS)),
+        fee_token_address: config.fee_token_address,
         vm_resource_fee_cost: HashMap::from([
             (String::from("n_steps"), 1_f64),
             (String::from("pedersen"), 1_f64),
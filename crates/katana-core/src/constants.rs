@@ -6,6 +6,18 @@ use crate::util::get_contract_class;
 
 pub const DEFAULT_GAS_PRICE: u128 = 100 * u128::pow(10, 9); // Given in units of wei.
 
+// Block timestamps used in deterministic mode
+
+pub const DETERMINISTIC_GENESIS_TIMESTAMP: u64 = 1_672_531_200; // 2023-01-01T00:00:00Z
+pub const DETERMINISTIC_BLOCK_TIME: u64 = 1; // Given in seconds.
+
+// The number of pending txs buffered for each subscriber before the slowest ones start missing some.
+pub const PENDING_TRANSACTIONS_CHANNEL_CAPACITY: usize = 1024;
+
+// The number of sealed blocks and reorgs buffered for each subscriber before the slowest ones start
+// missing some.
+pub const CHAIN_NOTIFICATIONS_CHANNEL_CAPACITY: usize = 256;
+
 // Contract artifacts path
 
 pub const ERC20_CONTRACT_PATH: &str = "./contracts/compiled/erc20.json";
@@ -0,0 +1,152 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+    str::FromStr,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use blockifier::abi::abi_utils::get_storage_var_address;
+use serde::{
+    de::{Error as _, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use starknet::core::types::FieldElement;
+use starknet_api::{
+    core::{ContractAddress, PatriciaKey},
+    hash::StarkFelt,
+};
+use tracing::info;
+
+use crate::state::DictStateReader;
+
+/// The number of allocations between two progress logs.
+const PROGRESS_LOG_INTERVAL: usize = 10_000;
+
+#[derive(Debug, Deserialize)]
+struct RawAllocation {
+    address: String,
+    balance: String,
+}
+
+/// Sets the fee token balances listed in an allocations file in the genesis state, and returns the
+/// number of allocations.
+///
+/// The file is either a CSV file with one `address,balance` pair per line (with an optional
+/// header), or a JSON array of `{ "address": ..., "balance": ... }` objects. Values are either
+/// hex (`0x` prefixed) or decimal. Listing the same address twice, or an address which already
+/// has a balance in `state` (such as a predeployed account), is an error.
+///
+/// Allocations are applied one at a time as the file is read, so that large files don't have to
+/// be loaded in memory.
+pub fn deploy_allocations(
+    state: &mut DictStateReader,
+    fee_token_address: ContractAddress,
+    path: &Path,
+) -> Result<usize> {
+    let file = BufReader::new(
+        File::open(path).with_context(|| format!("failed to open {}", path.display()))?,
+    );
+
+    let mut addresses = HashSet::new();
+    let mut allocate = |entry: usize, raw: RawAllocation| -> Result<()> {
+        let (address, balance) =
+            parse_allocation(&raw).with_context(|| format!("invalid allocation #{entry}"))?;
+
+        if !addresses.insert(address) {
+            bail!(
+                "duplicate allocation #{entry} for address {}",
+                raw.address.trim()
+            );
+        }
+
+        let balance_key = (
+            fee_token_address,
+            get_storage_var_address("ERC20_balances", &[*address.0.key()])?,
+        );
+        if state.storage_view.contains_key(&balance_key) {
+            bail!(
+                "allocation #{entry} for address {} would overwrite its existing balance",
+                raw.address.trim()
+            );
+        }
+        state.storage_view.insert(balance_key, balance);
+
+        if addresses.len() % PROGRESS_LOG_INTERVAL == 0 {
+            info!("Loaded {} genesis allocations so far", addresses.len());
+        }
+
+        Ok(())
+    };
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => {
+            for (index, line) in file.lines().enumerate() {
+                let line = line?;
+                let line = line.trim();
+
+                if line.is_empty() || (index == 0 && line.starts_with("address")) {
+                    continue;
+                }
+
+                let (address, balance) = line
+                    .split_once(',')
+                    .ok_or(anyhow!("missing balance on line {}", index + 1))?;
+
+                allocate(
+                    index + 1,
+                    RawAllocation {
+                        address: address.to_string(),
+                        balance: balance.to_string(),
+                    },
+                )?;
+            }
+        }
+
+        Some("json") => {
+            let mut deserializer = serde_json::Deserializer::from_reader(file);
+            deserializer.deserialize_seq(AllocationsVisitor(&mut allocate))?;
+            deserializer.end()?;
+        }
+
+        _ => bail!("allocations file must be a .csv or .json file"),
+    }
+
+    Ok(addresses.len())
+}
+
+/// Applies the allocations of a JSON array as they are deserialized, instead of collecting them
+/// first.
+struct AllocationsVisitor<F>(F);
+
+impl<'de, F> Visitor<'de> for AllocationsVisitor<F>
+where
+    F: FnMut(usize, RawAllocation) -> Result<()>,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("an array of allocations")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut index = 0;
+        while let Some(raw) = seq.next_element::<RawAllocation>()? {
+            index += 1;
+            (self.0)(index, raw).map_err(|err| A::Error::custom(format!("{err:#}")))?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_allocation(raw: &RawAllocation) -> Result<(ContractAddress, StarkFelt)> {
+    let address = FieldElement::from_str(raw.address.trim())?;
+    let balance = FieldElement::from_str(raw.balance.trim())?;
+
+    Ok((
+        ContractAddress(PatriciaKey::try_from(StarkFelt::from(address))?),
+        StarkFelt::from(balance),
+    ))
+}
@@ -1,6 +1,7 @@
 pub mod accounts;
 pub mod block_context;
 pub mod constants;
+pub mod genesis;
 pub mod sequencer;
 pub mod starknet;
 pub mod state;
@@ -1,12 +1,17 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use starknet::{
-    core::types::{FeeEstimate, FeeUnit},
+    core::types::{FeeEstimate, FeeUnit, TransactionStatus},
     providers::jsonrpc::models::{BlockId, BlockTag, StateUpdate},
 };
 
 use crate::{
+    accounts::Account,
     starknet::{
-        block::StarknetBlock, event::EmittedEvent, transaction::ExternalFunctionCall,
+        block::StarknetBlock,
+        event::{EmittedEvent, EventCursor, EventOrder},
+        notification::ChainNotification,
+        policy::DeclarePolicy,
+        transaction::{ClassExecutionStats, EntrypointStats, ExternalFunctionCall},
         StarknetConfig, StarknetWrapper,
     },
     util::starkfelt_to_u128,
@@ -17,33 +22,61 @@ use blockifier::{
     fee::fee_utils::{calculate_l1_gas_by_vm_usage, extract_l1_gas_and_vm_usage},
     state::state_api::{State, StateReader},
     transaction::{
-        account_transaction::AccountTransaction, transaction_execution::Transaction,
-        transactions::ExecutableTransaction,
+        account_transaction::AccountTransaction,
+        objects::TransactionExecutionInfo,
+        transaction_execution::Transaction,
+        transactions::{DeclareTransaction, ExecutableTransaction},
     },
 };
+use cairo_lang_starknet::casm_contract_class::CasmContractClass;
 // use starknet::providers::jsonrpc::models::BlockId;
 use starknet_api::{
-    block::{BlockHash, BlockNumber},
-    core::{calculate_contract_address, ChainId, ClassHash, ContractAddress, Nonce},
+    block::{BlockHash, BlockNumber, GasPrice},
+    core::{
+        calculate_contract_address, ChainId, ClassHash, ContractAddress, EntryPointSelector, Nonce,
+    },
     hash::StarkFelt,
     stark_felt,
     state::StorageKey,
     transaction::{
-        Calldata, ContractAddressSalt, DeployAccountTransaction, Fee,
-        Transaction as StarknetApiTransaction, TransactionHash, TransactionSignature,
-        TransactionVersion,
+        Calldata, ContractAddressSalt, DeclareTransaction as StarknetApiDeclareTransaction,
+        DeployAccountTransaction, Fee, MessageToL1, Transaction as StarknetApiTransaction,
+        TransactionHash, TransactionSignature, TransactionVersion,
     },
 };
+use tokio::sync::broadcast;
+
+/// The reasons a bundle of transactions can't be estimated.
+#[derive(Debug, thiserror::Error)]
+pub enum EstimateBundleError {
+    #[error("block {0:?} not found")]
+    BlockNotFound(BlockId),
+    #[error("transaction {index} of the bundle failed: {reason}")]
+    TransactionFailed { index: usize, reason: String },
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// The reasons a declare transaction can't be submitted.
+#[derive(Debug, thiserror::Error)]
+pub enum DeclareError {
+    #[error("the chain is frozen")]
+    ChainFrozen,
+    #[error("class {0} is not allowed to be declared")]
+    ClassNotDeclarable(StarkFelt),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
 
 pub struct KatanaSequencer {
     pub starknet: StarknetWrapper,
 }
 
 impl KatanaSequencer {
-    pub fn new(config: StarknetConfig) -> Self {
-        Self {
-            starknet: StarknetWrapper::new(config),
-        }
+    pub fn new(config: StarknetConfig) -> Result<Self> {
+        Ok(Self {
+            starknet: StarknetWrapper::new(config)?,
+        })
     }
 
     // The starting point of the sequencer
@@ -61,6 +94,8 @@ impl KatanaSequencer {
         signature: TransactionSignature,
         balance: u64,
     ) -> anyhow::Result<(TransactionHash, ContractAddress)> {
+        self.ensure_not_frozen()?;
+
         let contract_address = calculate_contract_address(
             contract_address_salt,
             class_hash,
@@ -86,6 +121,111 @@ impl KatanaSequencer {
             signature,
         )
     }
+
+    /// Returns the events emitted in a block which match the given filter, in the order they
+    /// were emitted.
+    fn block_events(
+        &self,
+        block_number: BlockNumber,
+        address: &Option<StarkFelt>,
+        keys: &Option<Vec<Vec<StarkFelt>>>,
+    ) -> Result<Vec<EmittedEvent>, blockifier::state::errors::StateError> {
+        let block = self.starknet.blocks.by_number(block_number).ok_or(
+            blockifier::state::errors::StateError::StateReadError("block not found".into()),
+        )?;
+
+        let mut events = Vec::new();
+        for tx in block.transactions() {
+            match tx {
+                StarknetApiTransaction::Invoke(_) | StarknetApiTransaction::L1Handler(_) => {}
+                _ => continue,
+            }
+
+            let sn_tx = self
+                .starknet
+                .transactions
+                .transactions
+                .get(&tx.transaction_hash())
+                .ok_or(blockifier::state::errors::StateError::StateReadError(
+                    "transaction not found".to_string(),
+                ))?;
+
+            events.extend(
+                sn_tx
+                    .emitted_events()
+                    .iter()
+                    .filter(|event| {
+                        // Check the address condition
+                        let address_condition = match &address {
+                            Some(a) => a == event.from_address.0.key(),
+                            None => true,
+                        };
+
+                        // If the address condition is false, no need to check the keys
+                        if !address_condition {
+                            return false;
+                        }
+
+                        // Check the keys condition
+                        match &keys {
+                            Some(keys) => {
+                                // "Per key (by position), designate the possible values to be matched
+                                // for events to be returned. Empty array designates 'any' value"
+                                let keys_to_check =
+                                    std::cmp::min(keys.len(), event.content.keys.len());
+
+                                event
+                                    .content
+                                    .keys
+                                    .iter()
+                                    .zip(keys.iter())
+                                    .take(keys_to_check)
+                                    .all(|(key, filter)| filter.contains(&key.0))
+                            }
+                            None => true,
+                        }
+                    })
+                    .map(|event| EmittedEvent {
+                        inner: event.clone(),
+                        block_hash: block.block_hash(),
+                        block_number: block.block_number(),
+                        transaction_hash: tx.transaction_hash(),
+                    })
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        Ok(events)
+    }
+
+    fn ensure_not_frozen(&self) -> Result<()> {
+        if self.starknet.frozen {
+            bail!("the chain is frozen");
+        }
+        Ok(())
+    }
+
+    fn fee_estimate_from_execution_info(
+        &self,
+        exec_info: &TransactionExecutionInfo,
+    ) -> Result<FeeEstimate> {
+        let (l1_gas_usage, vm_resources) = extract_l1_gas_and_vm_usage(&exec_info.actual_resources);
+        let l1_gas_by_vm_usage =
+            calculate_l1_gas_by_vm_usage(&self.starknet.block_context, &vm_resources)?;
+
+        let total_l1_gas_usage = l1_gas_usage as f64 + l1_gas_by_vm_usage;
+
+        let overall_fee = (total_l1_gas_usage.ceil() as u64)
+            .checked_mul(self.starknet.block_context.gas_price as u64)
+            .ok_or_else(|| anyhow!("overall fee overflows"))?;
+
+        Ok(FeeEstimate {
+            unit: FeeUnit::Wei,
+            overall_fee,
+            gas_usage: total_l1_gas_usage.ceil() as u64,
+            gas_price: self.starknet.block_context.gas_price as u64,
+        })
+    }
 }
 
 impl Sequencer for KatanaSequencer {
@@ -97,6 +237,8 @@ impl Sequencer for KatanaSequencer {
         constructor_calldata: Calldata,
         signature: TransactionSignature,
     ) -> anyhow::Result<(TransactionHash, ContractAddress)> {
+        self.ensure_not_frozen()?;
+
         let contract_address = calculate_contract_address(
             contract_address_salt,
             class_hash,
@@ -136,10 +278,56 @@ impl Sequencer for KatanaSequencer {
     }
 
     fn add_account_transaction(&mut self, transaction: AccountTransaction) -> Result<()> {
+        self.ensure_not_frozen()?;
         self.starknet
             .handle_transaction(Transaction::AccountTransaction(transaction))
     }
 
+    fn add_declare_transaction(
+        &mut self,
+        transaction: DeclareTransaction,
+        abi: Option<String>,
+        casm: Option<CasmContractClass>,
+    ) -> Result<(), DeclareError> {
+        if self.starknet.frozen {
+            return Err(DeclareError::ChainFrozen);
+        }
+
+        let (class_hash, transaction_hash) = match &transaction.tx {
+            StarknetApiDeclareTransaction::V0(tx) | StarknetApiDeclareTransaction::V1(tx) => {
+                (tx.class_hash, tx.transaction_hash)
+            }
+            StarknetApiDeclareTransaction::V2(tx) => (tx.class_hash, tx.transaction_hash),
+        };
+
+        if !self.starknet.config.declare_policy.allows(&class_hash) {
+            return Err(DeclareError::ClassNotDeclarable(class_hash.0));
+        }
+
+        self.starknet
+            .handle_transaction(Transaction::AccountTransaction(
+                AccountTransaction::Declare(transaction),
+            ))?;
+
+        let is_declared = self
+            .starknet
+            .transactions
+            .transactions
+            .get(&transaction_hash)
+            .map_or(false, |tx| tx.status != TransactionStatus::Rejected);
+
+        if is_declared {
+            if let Some(abi) = abi {
+                self.starknet.class_abis.insert(class_hash, abi);
+            }
+            if let Some(casm) = casm {
+                self.starknet.compiled_casms.insert(class_hash, casm);
+            }
+        }
+
+        Ok(())
+    }
+
     fn estimate_fee(
         &self,
         account_transaction: AccountTransaction,
@@ -155,19 +343,31 @@ impl Sequencer for KatanaSequencer {
             .starknet
             .simulate_transaction(account_transaction, Some(state))?;
 
-        let (l1_gas_usage, vm_resources) = extract_l1_gas_and_vm_usage(&exec_info.actual_resources);
-        let l1_gas_by_vm_usage =
-            calculate_l1_gas_by_vm_usage(&self.starknet.block_context, &vm_resources)?;
+        self.fee_estimate_from_execution_info(&exec_info)
+    }
 
-        let total_l1_gas_usage = l1_gas_usage as f64 + l1_gas_by_vm_usage;
+    fn estimate_fee_bundle(
+        &self,
+        transactions: Vec<AccountTransaction>,
+        block_id: BlockId,
+    ) -> Result<Vec<FeeEstimate>, EstimateBundleError> {
+        let state = self
+            .starknet
+            .state_from_block_id(block_id)
+            .ok_or(EstimateBundleError::BlockNotFound(block_id))?;
 
-        Ok(FeeEstimate {
-            unit: FeeUnit::Wei,
-            overall_fee: total_l1_gas_usage.ceil() as u64
-                * self.starknet.block_context.gas_price as u64,
-            gas_usage: total_l1_gas_usage.ceil() as u64,
-            gas_price: self.starknet.block_context.gas_price as u64,
-        })
+        self.starknet
+            .simulate_transactions(transactions, Some(state))
+            .map_err(|(index, err)| EstimateBundleError::TransactionFailed {
+                index,
+                reason: err.to_string(),
+            })?
+            .iter()
+            .map(|exec_info| {
+                self.fee_estimate_from_execution_info(exec_info)
+                    .map_err(EstimateBundleError::from)
+            })
+            .collect()
     }
 
     fn block_hash_and_number(&self) -> Option<(BlockHash, BlockNumber)> {
@@ -175,16 +375,47 @@ impl Sequencer for KatanaSequencer {
         Some((block.block_hash(), block.block_number()))
     }
 
+    fn class_abi(&self, class_hash: ClassHash) -> Option<String> {
+        self.starknet.class_abis.get(&class_hash).cloned()
+    }
+
+    fn compiled_casm(&self, class_hash: ClassHash) -> Option<CasmContractClass> {
+        self.starknet.compiled_casms.get(&class_hash).cloned()
+    }
+
+    fn gas_price_history(
+        &self,
+        from_block: BlockNumber,
+        to_block: Option<BlockNumber>,
+    ) -> Vec<(BlockNumber, GasPrice)> {
+        let Some(latest) = self.starknet.blocks.current_block_number() else {
+            return vec![];
+        };
+
+        let to_block = to_block.map_or(latest, |to_block| to_block.min(latest));
+
+        (from_block.0..=to_block.0)
+            .filter_map(|number| self.starknet.blocks.num_to_block.get(&BlockNumber(number)))
+            .map(|block| (block.block_number(), block.header().gas_price))
+            .collect()
+    }
+
     fn class_hash_at(
-        &mut self,
+        &self,
         _block_id: BlockId,
         contract_address: ContractAddress,
     ) -> Result<ClassHash, blockifier::state::errors::StateError> {
-        self.starknet.state.get_class_hash_at(contract_address)
+        Ok(self
+            .starknet
+            .state
+            .address_to_class_hash
+            .get(&contract_address)
+            .copied()
+            .unwrap_or_default())
     }
 
     fn storage_at(
-        &mut self,
+        &self,
         contract_address: ContractAddress,
         storage_key: StorageKey,
         block_id: BlockId,
@@ -218,11 +449,36 @@ impl Sequencer for KatanaSequencer {
     }
 
     fn nonce_at(
-        &mut self,
-        _block_id: BlockId,
+        &self,
+        block_id: BlockId,
         contract_address: ContractAddress,
     ) -> Result<Nonce, blockifier::state::errors::StateError> {
-        self.starknet.state.get_nonce_at(contract_address)
+        let state = self.starknet.state_from_block_id(block_id).ok_or(
+            blockifier::state::errors::StateError::StateReadError(format!(
+                "block {block_id:?} not found",
+            )),
+        )?;
+
+        Ok(state
+            .address_to_nonce
+            .get(&contract_address)
+            .copied()
+            .unwrap_or_default())
+    }
+
+    fn is_class_declared(&self, class_hash: ClassHash) -> bool {
+        self.starknet
+            .pending_state()
+            .class_hash_to_class
+            .contains_key(&class_hash)
+    }
+
+    fn predeployed_accounts(&self) -> Vec<Account> {
+        self.starknet.predeployed_accounts.accounts.clone()
+    }
+
+    fn universal_deployer_address(&self) -> ContractAddress {
+        self.starknet.config.universal_deployer_address
     }
 
     fn call(
@@ -242,6 +498,78 @@ impl Sequencer for KatanaSequencer {
         self.starknet.transactions.by_hash(hash)
     }
 
+    fn rejected_transaction_reason(&self, hash: &TransactionHash) -> Option<String> {
+        self.starknet.transactions.rejection_reason(hash)
+    }
+
+    fn subscribe_pending_transactions(&self) -> broadcast::Receiver<StarknetApiTransaction> {
+        self.starknet.subscribe_pending_transactions()
+    }
+
+    fn subscribe_chain_notifications(&self) -> broadcast::Receiver<ChainNotification> {
+        self.starknet.subscribe_chain_notifications()
+    }
+
+    fn transaction_status(&self, hash: &TransactionHash) -> Option<TransactionStatus> {
+        self.starknet
+            .transactions
+            .transactions
+            .get(hash)
+            .map(|tx| tx.status)
+    }
+
+    fn entrypoint_stats(&self) -> Option<Vec<(ClassHash, EntryPointSelector, EntrypointStats)>> {
+        if !self.starknet.config.trace_entrypoints {
+            return None;
+        }
+
+        let mut stats = self
+            .starknet
+            .entrypoint_stats
+            .iter()
+            .map(|((class_hash, selector), stats)| (*class_hash, *selector, *stats))
+            .collect::<Vec<_>>();
+        stats.sort_by(|(_, _, a), (_, _, b)| b.steps.cmp(&a.steps));
+        Some(stats)
+    }
+
+    fn send_message_to_l2(
+        &mut self,
+        from_address: StarkFelt,
+        to_address: ContractAddress,
+        selector: EntryPointSelector,
+        payload: Vec<StarkFelt>,
+    ) -> Result<TransactionHash> {
+        self.ensure_not_frozen()?;
+
+        self.starknet
+            .settlement
+            .send_message_to_l2(from_address, to_address, selector, payload);
+
+        // The message just sent is the last one of the queue.
+        self.starknet
+            .process_messages_to_l2()?
+            .pop()
+            .ok_or(anyhow!("message to L2 not processed"))
+    }
+
+    fn messages_to_l1(&self) -> Vec<(BlockNumber, MessageToL1)> {
+        self.starknet.settlement.messages_to_l1().to_vec()
+    }
+
+    fn class_execution_stats(
+        &self,
+        block_id: BlockId,
+    ) -> Option<Vec<(ClassHash, ClassExecutionStats)>> {
+        let mut stats = self
+            .starknet
+            .class_execution_stats(block_id)?
+            .into_iter()
+            .collect::<Vec<_>>();
+        stats.sort_by(|(_, a), (_, b)| b.steps.cmp(&a.steps));
+        Some(stats)
+    }
+
     fn events(
         &self,
         from_block: BlockId,
@@ -264,78 +592,86 @@ impl Sequencer for KatanaSequencer {
 
         let mut events = Vec::new();
         for i in from_block.0..to_block.0 {
-            let block = self.starknet.blocks.by_number(BlockNumber(i)).ok_or(
-                blockifier::state::errors::StateError::StateReadError("block not found".into()),
-            )?;
-
-            for tx in block.transactions() {
-                match tx {
-                    StarknetApiTransaction::Invoke(_) | StarknetApiTransaction::L1Handler(_) => {}
-                    _ => continue,
-                }
-
-                let sn_tx = self
-                    .starknet
-                    .transactions
-                    .transactions
-                    .get(&tx.transaction_hash())
-                    .ok_or(blockifier::state::errors::StateError::StateReadError(
-                        "transaction not found".to_string(),
-                    ))?;
-
-                events.extend(
-                    sn_tx
-                        .emitted_events()
-                        .iter()
-                        .filter(|event| {
-                            // Check the address condition
-                            let address_condition = match &address {
-                                Some(a) => a != event.from_address.0.key(),
-                                None => true,
-                            };
-
-                            // If the address condition is false, no need to check the keys
-                            if !address_condition {
-                                return false;
-                            }
-
-                            // Check the keys condition
-                            match &keys {
-                                Some(keys) => {
-                                    // "Per key (by position), designate the possible values to be matched
-                                    // for events to be returned. Empty array designates 'any' value"
-                                    let keys_to_check =
-                                        std::cmp::min(keys.len(), event.content.keys.len());
-
-                                    event
-                                        .content
-                                        .keys
-                                        .iter()
-                                        .zip(keys.iter())
-                                        .take(keys_to_check)
-                                        .all(|(key, filter)| filter.contains(&key.0))
-                                }
-                                None => true,
-                            }
-                        })
-                        .map(|event| EmittedEvent {
-                            inner: event.clone(),
-                            block_hash: block.block_hash(),
-                            block_number: block.block_number(),
-                            transaction_hash: tx.transaction_hash(),
-                        })
-                        .collect::<Vec<_>>(),
-                );
-            }
+            events.extend(self.block_events(BlockNumber(i), &address, &keys)?);
         }
 
         Ok(events)
     }
 
+    fn events_page(
+        &self,
+        from_block: BlockNumber,
+        to_block: BlockNumber,
+        address: Option<StarkFelt>,
+        keys: Option<Vec<Vec<StarkFelt>>>,
+        order: EventOrder,
+        cursor: Option<EventCursor>,
+        chunk_size: u64,
+    ) -> Result<(Vec<EmittedEvent>, Option<EventCursor>), blockifier::state::errors::StateError>
+    {
+        if cursor.map_or(false, |cursor| {
+            !cursor.is_valid_for(order, from_block, to_block)
+        }) {
+            return Err(blockifier::state::errors::StateError::StateReadError(
+                "continuation token does not match the query".into(),
+            ));
+        }
+
+        // Start directly from the block of the cursor instead of scanning the whole range again.
+        let (mut block_number, mut offset) = match (cursor, order) {
+            (Some(cursor), _) => (cursor.block_number.0, cursor.offset),
+            (None, EventOrder::Ascending) => (from_block.0, 0),
+            (None, EventOrder::Descending) => (to_block.0, 0),
+        };
+
+        let mut events = Vec::new();
+        loop {
+            let mut block_events = self.block_events(BlockNumber(block_number), &address, &keys)?;
+            if order == EventOrder::Descending {
+                block_events.reverse();
+            }
+
+            let remaining = block_events.len().saturating_sub(offset);
+            let needed = chunk_size as usize - events.len();
+            events.extend(block_events.into_iter().skip(offset).take(needed));
+
+            if remaining > needed {
+                let cursor = EventCursor {
+                    order,
+                    block_number: BlockNumber(block_number),
+                    offset: offset + needed,
+                };
+                return Ok((events, Some(cursor)));
+            }
+
+            let next_block = match order {
+                EventOrder::Ascending if block_number < to_block.0 => block_number + 1,
+                EventOrder::Descending if block_number > from_block.0 => block_number - 1,
+                _ => return Ok((events, None)),
+            };
+
+            if remaining == needed {
+                let cursor = EventCursor {
+                    order,
+                    block_number: BlockNumber(next_block),
+                    offset: 0,
+                };
+                return Ok((events, Some(cursor)));
+            }
+
+            block_number = next_block;
+            offset = 0;
+        }
+    }
+
     fn state_update(
         &self,
         block_id: BlockId,
     ) -> Result<StateUpdate, blockifier::state::errors::StateError> {
+        if let BlockId::Tag(BlockTag::Pending) = block_id {
+            return Ok(self.starknet.pending_state_update());
+        }
+
         let block_number = self.starknet.block_number_from_block_id(block_id).ok_or(
             blockifier::state::errors::StateError::StateReadError(format!(
                 "block id {block_id:?} not found",
@@ -349,24 +685,211 @@ impl Sequencer for KatanaSequencer {
         )
     }
 
+    fn declare_policy(&self) -> DeclarePolicy {
+        self.starknet.config.declare_policy.clone()
+    }
+
+    fn set_declare_policy(&mut self, policy: DeclarePolicy) {
+        self.starknet.config.declare_policy = policy;
+    }
+
+    fn snapshot(&mut self) -> Result<u64> {
+        self.starknet.take_snapshot()
+    }
+
+    fn revert(&mut self, snapshot_id: u64) -> Result<bool> {
+        self.ensure_not_frozen()?;
+        Ok(self.starknet.revert_to_snapshot(snapshot_id))
+    }
+
+    fn set_storage_at(
+        &mut self,
+        contract_address: ContractAddress,
+        storage_key: StorageKey,
+        value: StarkFelt,
+    ) -> Result<()> {
+        self.ensure_not_frozen()?;
+        self.starknet.override_state(|state| {
+            state
+                .storage_view
+                .insert((contract_address, storage_key), value);
+        });
+        Ok(())
+    }
+
+    fn set_nonce(&mut self, contract_address: ContractAddress, nonce: Nonce) -> Result<()> {
+        self.ensure_not_frozen()?;
+        self.starknet.override_state(|state| {
+            state.address_to_nonce.insert(contract_address, nonce);
+        });
+        Ok(())
+    }
+
+    fn set_balance(&mut self, contract_address: ContractAddress, balance: u128) -> Result<()> {
+        let balance_key =
+            get_storage_var_address("ERC20_balances", &[*contract_address.0.key()]).unwrap();
+        let fee_token_address = self.starknet.block_context.fee_token_address;
+        self.set_storage_at(fee_token_address, balance_key, StarkFelt::from(balance))
+    }
+
+    fn set_class_hash_at(
+        &mut self,
+        contract_address: ContractAddress,
+        class_hash: ClassHash,
+    ) -> Result<()> {
+        self.ensure_not_frozen()?;
+        if !self.is_class_declared(class_hash) {
+            bail!("class {} is not declared", class_hash.0);
+        }
+        self.starknet.override_state(|state| {
+            state
+                .address_to_class_hash
+                .insert(contract_address, class_hash);
+        });
+        Ok(())
+    }
+
+    fn increase_time(&mut self, seconds: u64) -> Result<()> {
+        self.ensure_not_frozen()?;
+        self.starknet.increase_time(seconds)
+    }
+
+    fn set_next_block_timestamp(&mut self, timestamp: u64) -> Result<()> {
+        self.ensure_not_frozen()?;
+        self.starknet.set_next_block_timestamp(timestamp)
+    }
+
+    fn set_auto_mining(&mut self, enabled: bool) -> Result<()> {
+        self.ensure_not_frozen()?;
+
+        // The transactions left waiting in the pending block are mined right away.
+        let has_pending_transactions = self
+            .starknet
+            .blocks
+            .pending_block
+            .as_ref()
+            .map_or(false, |block| !block.transactions().is_empty());
+        if enabled && has_pending_transactions {
+            self.generate_new_block()?;
+        }
+
+        self.starknet.config.blocks_on_demand = !enabled;
+        Ok(())
+    }
+
+    fn is_auto_mining(&self) -> bool {
+        !self.starknet.config.blocks_on_demand
+    }
+
+    fn set_frozen(&mut self, frozen: bool) {
+        self.starknet.frozen = frozen;
+    }
+
+    fn is_frozen(&self) -> bool {
+        self.starknet.frozen
+    }
+
     fn generate_new_block(&mut self) -> Result<()> {
+        self.ensure_not_frozen()?;
         self.starknet.generate_latest_block()?;
         self.starknet.generate_pending_block();
         Ok(())
     }
+
+    fn mine_blocks(&mut self, num_blocks: u64, interval: Option<u64>) -> Result<()> {
+        self.ensure_not_frozen()?;
+        self.starknet.mine_blocks(num_blocks, interval)
+    }
+
+    fn replay_blocks(
+        &self,
+        from_block: BlockNumber,
+        to_block: BlockNumber,
+    ) -> Vec<(BlockNumber, Result<bool>)> {
+        (from_block.0..=to_block.0)
+            .map(BlockNumber)
+            .map(|number| (number, self.starknet.replay_block(number)))
+            .collect()
+    }
 }
 
 pub trait Sequencer {
     fn chain_id(&self) -> ChainId;
 
+    fn declare_policy(&self) -> DeclarePolicy;
+
+    /// Takes a snapshot of the chain and returns its id.
+    fn snapshot(&mut self) -> Result<u64>;
+
+    /// Reverts the chain to a snapshot. Returns `false` if the snapshot doesn't exist.
+    fn revert(&mut self, snapshot_id: u64) -> Result<bool>;
+
+    /// Replaces the policy restricting which classes can be declared. Classes already declared
+    /// are not affected.
+    fn set_declare_policy(&mut self, policy: DeclarePolicy);
+
+    /// Sets the value of a storage slot, bypassing transaction execution. The change is reflected
+    /// in both the latest and the pending state.
+    fn set_storage_at(
+        &mut self,
+        contract_address: ContractAddress,
+        storage_key: StorageKey,
+        value: StarkFelt,
+    ) -> Result<()>;
+
+    fn set_nonce(&mut self, contract_address: ContractAddress, nonce: Nonce) -> Result<()>;
+
+    /// Sets the fee token balance of a contract.
+    fn set_balance(&mut self, contract_address: ContractAddress, balance: u128) -> Result<()>;
+
+    /// Replaces the class of a deployed contract, or deploys one if there is no contract at the
+    /// address. Fails if the class isn't declared.
+    fn set_class_hash_at(
+        &mut self,
+        contract_address: ContractAddress,
+        class_hash: ClassHash,
+    ) -> Result<()>;
+
+    /// Moves the time of the chain forward by `seconds`, starting with the pending block.
+    fn increase_time(&mut self, seconds: u64) -> Result<()>;
+
+    /// Sets the timestamp of the pending block, the following blocks continuing from it.
+    fn set_next_block_timestamp(&mut self, timestamp: u64) -> Result<()>;
+
+    /// Switches between mining a block for every transaction and mining blocks on demand.
+    fn set_auto_mining(&mut self, enabled: bool) -> Result<()>;
+
+    fn is_auto_mining(&self) -> bool;
+
+    /// Freezes or unfreezes the chain. While frozen, transactions are rejected and no block is
+    /// produced.
+    fn set_frozen(&mut self, frozen: bool);
+
+    fn is_frozen(&self) -> bool;
+
     fn generate_new_block(&mut self) -> Result<()>;
 
+    fn mine_blocks(&mut self, num_blocks: u64, interval: Option<u64>) -> Result<()>;
+
+    /// Replays each block of the range, failing independently for each block.
+    fn replay_blocks(
+        &self,
+        from_block: BlockNumber,
+        to_block: BlockNumber,
+    ) -> Vec<(BlockNumber, Result<bool>)>;
+
     fn nonce_at(
-        &mut self,
+        &self,
         block_id: BlockId,
         contract_address: ContractAddress,
     ) -> Result<Nonce, blockifier::state::errors::StateError>;
 
+    fn is_class_declared(&self, class_hash: ClassHash) -> bool;
+
+    fn predeployed_accounts(&self) -> Vec<Account>;
+
+    fn universal_deployer_address(&self) -> ContractAddress;
+
     fn block_number(&self) -> BlockNumber;
 
     fn block(&self, block_id: BlockId) -> Option<StarknetBlock>;
@@ -374,8 +897,39 @@ pub trait Sequencer {
     fn transaction(&self, hash: &TransactionHash)
         -> Option<starknet_api::transaction::Transaction>;
 
-    fn class_hash_at(
+    fn rejected_transaction_reason(&self, hash: &TransactionHash) -> Option<String>;
+
+    fn subscribe_pending_transactions(&self) -> broadcast::Receiver<StarknetApiTransaction>;
+
+    /// Notifies every sealed block and every reorg caused by reverting to a snapshot.
+    fn subscribe_chain_notifications(&self) -> broadcast::Receiver<ChainNotification>;
+
+    fn transaction_status(&self, hash: &TransactionHash) -> Option<TransactionStatus>;
+
+    /// The accumulated cost of each entrypoint called so far, sorted by steps. Returns `None` if
+    /// entrypoint tracing is disabled.
+    fn entrypoint_stats(&self) -> Option<Vec<(ClassHash, EntryPointSelector, EntrypointStats)>>;
+
+    /// Sends a message from L1 through the mock settlement layer and executes the L1 handler
+    /// transaction consuming it, returning the hash of the transaction.
+    fn send_message_to_l2(
         &mut self,
+        from_address: StarkFelt,
+        to_address: ContractAddress,
+        selector: EntryPointSelector,
+        payload: Vec<StarkFelt>,
+    ) -> Result<TransactionHash>;
+
+    /// The messages sent to L1 by the transactions of the sealed blocks.
+    fn messages_to_l1(&self) -> Vec<(BlockNumber, MessageToL1)>;
+
+    fn class_execution_stats(
+        &self,
+        block_id: BlockId,
+    ) -> Option<Vec<(ClassHash, ClassExecutionStats)>>;
+
+    fn class_hash_at(
+        &self,
         block_id: BlockId,
         contract_address: ContractAddress,
     ) -> Result<ClassHash, blockifier::state::errors::StateError>;
@@ -389,7 +943,7 @@ pub trait Sequencer {
     ) -> Result<Vec<StarkFelt>>;
 
     fn storage_at(
-        &mut self,
+        &self,
         contract_address: ContractAddress,
         storage_key: StorageKey,
         block_id: BlockId,
@@ -406,12 +960,37 @@ pub trait Sequencer {
 
     fn add_account_transaction(&mut self, transaction: AccountTransaction) -> Result<()>;
 
+    fn add_declare_transaction(
+        &mut self,
+        transaction: DeclareTransaction,
+        abi: Option<String>,
+        casm: Option<CasmContractClass>,
+    ) -> Result<(), DeclareError>;
+
+    fn class_abi(&self, class_hash: ClassHash) -> Option<String>;
+
+    fn compiled_casm(&self, class_hash: ClassHash) -> Option<CasmContractClass>;
+
+    fn gas_price_history(
+        &self,
+        from_block: BlockNumber,
+        to_block: Option<BlockNumber>,
+    ) -> Vec<(BlockNumber, GasPrice)>;
+
     fn estimate_fee(
         &self,
         account_transaction: AccountTransaction,
         block_id: BlockId,
     ) -> Result<FeeEstimate>;
 
+    /// Estimates the fees of transactions executed one after the other, each one on top of the
+    /// changes made by the previous ones.
+    fn estimate_fee_bundle(
+        &self,
+        transactions: Vec<AccountTransaction>,
+        block_id: BlockId,
+    ) -> Result<Vec<FeeEstimate>, EstimateBundleError>;
+
     fn events(
         &self,
         from_block: BlockId,
@@ -422,6 +1001,20 @@ pub trait Sequencer {
         chunk_size: u64,
     ) -> Result<Vec<EmittedEvent>, blockifier::state::errors::StateError>;
 
+    /// Returns a page of at most `chunk_size` events emitted between `from_block` and `to_block`
+    /// (both inclusive), along with the cursor of the next page if there are more events.
+    #[allow(clippy::too_many_arguments)]
+    fn events_page(
+        &self,
+        from_block: BlockNumber,
+        to_block: BlockNumber,
+        address: Option<StarkFelt>,
+        keys: Option<Vec<Vec<StarkFelt>>>,
+        order: EventOrder,
+        cursor: Option<EventCursor>,
+        chunk_size: u64,
+    ) -> Result<(Vec<EmittedEvent>, Option<EventCursor>), blockifier::state::errors::StateError>;
+
     fn state_update(
         &self,
         block_id: BlockId,
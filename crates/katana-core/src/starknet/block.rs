@@ -176,7 +176,24 @@ impl StarknetBlocks {
         self.state_archive.get(block_number)
     }
 
+    pub fn get_state_mut(&mut self, block_number: &BlockNumber) -> Option<&mut DictStateReader> {
+        self.state_archive.get_mut(block_number)
+    }
+
     pub fn store_state(&mut self, block_number: BlockNumber, state: DictStateReader) {
         self.state_archive.insert(block_number, state);
     }
+
+    /// Removes the blocks after the first `total_blocks` ones, along with their states.
+    pub fn truncate(&mut self, total_blocks: usize) {
+        let removed = (total_blocks as u64..self.num_to_block.len() as u64).map(BlockNumber);
+
+        for block_number in removed {
+            if let Some(block) = self.num_to_block.remove(&block_number) {
+                self.hash_to_num.remove(&block.block_hash());
+            }
+            self.state_archive.remove(&block_number);
+            self.num_to_state_update.remove(&block_number);
+        }
+    }
 }
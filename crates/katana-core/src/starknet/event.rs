@@ -1,3 +1,7 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
 use starknet_api::{
     block::{BlockHash, BlockNumber},
     transaction::{Event, TransactionHash},
@@ -9,3 +13,79 @@ pub struct EmittedEvent {
     pub block_number: BlockNumber,
     pub transaction_hash: TransactionHash,
 }
+
+/// The order in which events are iterated over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventOrder {
+    /// Oldest events first.
+    #[default]
+    Ascending,
+    /// Newest events first.
+    Descending,
+}
+
+/// The position where the next page of events starts.
+///
+/// It is exposed to clients as a continuation token of the form `<asc|desc>:<block>:<offset>`,
+/// where `offset` is the number of matching events of `block` that were already returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventCursor {
+    pub order: EventOrder,
+    pub block_number: BlockNumber,
+    pub offset: usize,
+}
+
+impl EventCursor {
+    /// Whether the cursor can be used to continue a query over the given blocks in the given
+    /// order.
+    pub fn is_valid_for(
+        &self,
+        order: EventOrder,
+        from_block: BlockNumber,
+        to_block: BlockNumber,
+    ) -> bool {
+        self.order == order && self.block_number >= from_block && self.block_number <= to_block
+    }
+}
+
+impl fmt::Display for EventCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let order = match self.order {
+            EventOrder::Ascending => "asc",
+            EventOrder::Descending => "desc",
+        };
+        write!(f, "{order}:{}:{}", self.block_number.0, self.offset)
+    }
+}
+
+impl FromStr for EventCursor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+        let order = match parts.next() {
+            Some("asc") => EventOrder::Ascending,
+            Some("desc") => EventOrder::Descending,
+            _ => return Err(anyhow!("invalid event order in continuation token")),
+        };
+        let block_number = parts
+            .next()
+            .ok_or(anyhow!("missing block number in continuation token"))?
+            .parse::<u64>()?;
+        let offset = parts
+            .next()
+            .ok_or(anyhow!("missing offset in continuation token"))?
+            .parse::<usize>()?;
+
+        if parts.next().is_some() {
+            return Err(anyhow!("unexpected data in continuation token"));
+        }
+
+        Ok(Self {
+            order,
+            block_number: BlockNumber(block_number),
+            offset,
+        })
+    }
+}
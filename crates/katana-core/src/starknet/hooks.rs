@@ -0,0 +1,33 @@
+use anyhow::Result;
+use blockifier::{block_context::BlockContext, transaction::transaction_execution::Transaction};
+
+use super::block::StarknetBlock;
+
+/// A hook called right before each block is sealed, allowing embedders to inject their own system
+/// transactions (e.g. oracle updates or keeper jobs) at the end of every block.
+///
+/// Hooks are called in the order they were registered, and their transactions are executed in
+/// the order they are returned. A hook returning an error is skipped without affecting the block
+/// or the other hooks.
+pub trait BlockBuildingHook: Send + Sync {
+    fn transactions(&self, block_context: &BlockContext) -> Result<Vec<Transaction>>;
+}
+
+/// A policy evaluated for every candidate transaction before it is executed, allowing embedders to
+/// enforce their own sequencing rules (e.g. only accept transactions targeting a set of contracts,
+/// or cap the number of transactions per sender in a block).
+///
+/// A transaction is only executed if all the registered policies accept it. Excluded transactions
+/// are stored as rejected, with the reason given by the first policy excluding them.
+pub trait InclusionPolicy: Send + Sync {
+    fn should_include(
+        &self,
+        transaction: &starknet_api::transaction::Transaction,
+        pending_block: &StarknetBlock,
+    ) -> bool;
+
+    /// The reason reported for the transactions excluded by this policy.
+    fn exclusion_reason(&self) -> String {
+        "excluded by inclusion policy".to_string()
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use starknet::core::{
+    crypto::compute_hash_on_elements, types::FieldElement, utils::cairo_short_string_to_felt,
+};
+use starknet_api::{
+    block::BlockNumber,
+    core::{ChainId, ContractAddress, EntryPointSelector, Nonce},
+    hash::StarkFelt,
+    stark_felt,
+    transaction::{
+        Calldata, L1HandlerTransaction, MessageToL1, TransactionHash, TransactionVersion,
+    },
+};
+
+use crate::util::field_element_to_starkfelt;
+
+/// A message sent from L1 to a contract on L2, consumed by executing an L1 handler transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageToL2 {
+    /// The L1 address of the sender of the message.
+    pub from_address: StarkFelt,
+    pub to_address: ContractAddress,
+    /// The selector of the L1 handler of `to_address` consuming the message.
+    pub selector: EntryPointSelector,
+    pub payload: Vec<StarkFelt>,
+    pub nonce: Nonce,
+}
+
+impl MessageToL2 {
+    /// The L1 handler transaction consuming the message on L2.
+    pub fn to_l1_handler_transaction(&self, chain_id: &ChainId) -> Result<L1HandlerTransaction> {
+        // The L1 sender is passed to the handler as its first argument.
+        let calldata = [vec![self.from_address], self.payload.clone()].concat();
+
+        let transaction_hash = compute_hash_on_elements(&[
+            cairo_short_string_to_felt("l1_handler")?,
+            FieldElement::ZERO, // version
+            (*self.to_address.0.key()).into(),
+            self.selector.0.into(),
+            compute_hash_on_elements(
+                &calldata
+                    .iter()
+                    .map(|felt| (*felt).into())
+                    .collect::<Vec<FieldElement>>(),
+            ),
+            FieldElement::ZERO, // max_fee
+            FieldElement::from_hex_be(&chain_id.as_hex())?,
+            self.nonce.0.into(),
+        ]);
+
+        Ok(L1HandlerTransaction {
+            transaction_hash: TransactionHash(field_element_to_starkfelt(&transaction_hash)),
+            version: TransactionVersion(stark_felt!(0)),
+            nonce: self.nonce,
+            contract_address: self.to_address,
+            entry_point_selector: self.selector,
+            calldata: Calldata(calldata.into()),
+        })
+    }
+}
+
+/// An in-memory settlement layer, standing in for the L1 core contract so that message flows can
+/// be tested without running an L1 node.
+///
+/// Messages to L2 are queued until they are processed by the sequencer, and the messages to L1
+/// sent by the transactions of the sealed blocks are kept in the order they were sent.
+#[derive(Debug, Clone, Default)]
+pub struct MockSettlement {
+    messages_to_l2: VecDeque<MessageToL2>,
+    messages_to_l1: Vec<(BlockNumber, MessageToL1)>,
+    next_nonce: u64,
+}
+
+impl MockSettlement {
+    /// Queues a message to L2 and returns the nonce assigned to it.
+    pub fn send_message_to_l2(
+        &mut self,
+        from_address: StarkFelt,
+        to_address: ContractAddress,
+        selector: EntryPointSelector,
+        payload: Vec<StarkFelt>,
+    ) -> Nonce {
+        let nonce = Nonce(stark_felt!(self.next_nonce));
+        self.next_nonce += 1;
+
+        self.messages_to_l2.push_back(MessageToL2 {
+            from_address,
+            to_address,
+            selector,
+            payload,
+            nonce,
+        });
+
+        nonce
+    }
+
+    /// Removes the queued messages to L2, in the order they were sent.
+    pub fn drain_messages_to_l2(&mut self) -> Vec<MessageToL2> {
+        self.messages_to_l2.drain(..).collect()
+    }
+
+    pub fn pending_messages_to_l2(&self) -> impl Iterator<Item = &MessageToL2> {
+        self.messages_to_l2.iter()
+    }
+
+    /// Records the messages to L1 sent by the transactions of a sealed block.
+    pub fn add_messages_to_l1(
+        &mut self,
+        block_number: BlockNumber,
+        messages: impl IntoIterator<Item = MessageToL1>,
+    ) {
+        self.messages_to_l1
+            .extend(messages.into_iter().map(|message| (block_number, message)));
+    }
+
+    /// The messages to L1 sent so far, along with the number of the block they were sent in.
+    pub fn messages_to_l1(&self) -> &[(BlockNumber, MessageToL1)] {
+        &self.messages_to_l1
+    }
+}
@@ -1,12 +1,15 @@
-use std::path::PathBuf;
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use blockifier::{
     block_context::BlockContext,
     execution::entry_point::{CallEntryPoint, CallInfo, ExecutionContext},
     state::{
         cached_state::{CachedState, CommitmentStateDiff, MutRefState},
-        state_api::State,
+        state_api::{State, StateReader},
     },
     transaction::{
         account_transaction::AccountTransaction,
@@ -16,34 +19,54 @@ use blockifier::{
         transactions::{DeclareTransaction, ExecutableTransaction},
     },
 };
+use cairo_lang_starknet::casm_contract_class::CasmContractClass;
 use starknet::{
     core::types::{FieldElement, TransactionStatus},
     providers::jsonrpc::models::{BlockId, BlockTag, PendingStateUpdate, StateUpdate},
 };
 use starknet_api::{
     block::{BlockHash, BlockNumber, BlockTimestamp, GasPrice},
-    core::GlobalRoot,
-    hash::StarkFelt,
-    stark_felt,
+    core::{ClassHash, ContractAddress, EntryPointSelector, GlobalRoot, PatriciaKey},
+    hash::{StarkFelt, StarkHash},
+    patricia_key, stark_felt,
+    transaction::TransactionHash,
 };
-use tracing::info;
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
 
 pub mod block;
 pub mod event;
+pub mod hooks;
+pub mod messaging;
+pub mod notification;
+pub mod policy;
+pub mod snapshot;
 pub mod transaction;
 
 use crate::{
     accounts::PredeployedAccounts,
     block_context::block_context_from_config,
-    constants::DEFAULT_PREFUNDED_ACCOUNT_BALANCE,
-    state::DictStateReader,
+    constants::{
+        CHAIN_NOTIFICATIONS_CHANNEL_CAPACITY, DEFAULT_GAS_PRICE, DEFAULT_PREFUNDED_ACCOUNT_BALANCE,
+        DETERMINISTIC_BLOCK_TIME, DETERMINISTIC_GENESIS_TIMESTAMP, FEE_TOKEN_ADDRESS,
+        PENDING_TRANSACTIONS_CHANNEL_CAPACITY, UDC_ADDRESS,
+    },
+    genesis::deploy_allocations,
+    state::{deploy_fee_contract, deploy_universal_deployer_contract, DictStateReader},
     util::{
-        convert_blockifier_tx_to_starknet_api_tx, convert_state_diff_to_rpc_state_diff,
-        get_current_timestamp,
+        convert_blockifier_tx_to_starknet_api_tx, convert_starknet_api_tx_to_blockifier_tx,
+        convert_state_diff_to_rpc_state_diff, get_current_timestamp,
     },
 };
 use block::{StarknetBlock, StarknetBlocks};
-use transaction::{StarknetTransaction, StarknetTransactions};
+use hooks::{BlockBuildingHook, InclusionPolicy};
+use messaging::MockSettlement;
+use notification::ChainNotification;
+use policy::DeclarePolicy;
+use snapshot::StarknetSnapshot;
+use transaction::{
+    ClassExecutionStats, EntrypointStats, StarknetTransaction, StarknetTransactions,
+};
 
 use self::transaction::ExternalFunctionCall;
 
@@ -56,6 +79,44 @@ pub struct StarknetConfig {
     pub blocks_on_demand: bool,
     pub allow_zero_max_fee: bool,
     pub account_path: Option<PathBuf>,
+    pub fee_token_address: ContractAddress,
+    pub universal_deployer_address: ContractAddress,
+    pub deterministic: bool,
+    pub genesis_allocations: Option<PathBuf>,
+    // Emit the resources used by each entrypoint call as trace events, and accumulate them per
+    // entrypoint.
+    pub trace_entrypoints: bool,
+    pub declare_policy: DeclarePolicy,
+}
+
+impl Default for StarknetConfig {
+    fn default() -> Self {
+        Self {
+            seed: [0u8; 32],
+            gas_price: DEFAULT_GAS_PRICE,
+            chain_id: String::from("KATANA"),
+            total_accounts: 10,
+            blocks_on_demand: false,
+            allow_zero_max_fee: false,
+            account_path: None,
+            fee_token_address: ContractAddress(patricia_key!(*FEE_TOKEN_ADDRESS)),
+            universal_deployer_address: ContractAddress(patricia_key!(*UDC_ADDRESS)),
+            deterministic: false,
+            genesis_allocations: None,
+            trace_entrypoints: false,
+            declare_policy: DeclarePolicy::default(),
+        }
+    }
+}
+
+impl StarknetConfig {
+    /// Checks that the options can be used together, as the CLI does when parsing them.
+    pub fn validate(&self) -> Result<()> {
+        if self.deterministic && self.seed != [0u8; 32] {
+            return Err(anyhow!("a seed can't be specified in deterministic mode"));
+        }
+        Ok(())
+    }
 }
 
 pub struct StarknetWrapper {
@@ -64,16 +125,44 @@ pub struct StarknetWrapper {
     pub block_context: BlockContext,
     pub transactions: StarknetTransactions,
     pub state: DictStateReader,
+    pub genesis_state: DictStateReader,
     pub predeployed_accounts: PredeployedAccounts,
     pub pending_state: CachedState<DictStateReader>,
+    // The ABIs of the declared Sierra classes, kept apart from the compiled classes so they can be
+    // served without going through the whole contract artifact.
+    pub class_abis: HashMap<ClassHash, String>,
+    // The CASM the declared Sierra classes were compiled to, as the blockifier classes can't be
+    // converted back to it.
+    pub compiled_casms: HashMap<ClassHash, CasmContractClass>,
+    pub block_building_hooks: Vec<Box<dyn BlockBuildingHook>>,
+    pub inclusion_policies: Vec<Box<dyn InclusionPolicy>>,
+    // Notified of every tx added to the pending block.
+    pub pending_transactions: broadcast::Sender<starknet_api::transaction::Transaction>,
+    // Notified of every sealed block and reorg.
+    pub chain_notifications: broadcast::Sender<ChainNotification>,
+    pub settlement: MockSettlement,
+    // Only recorded when `trace_entrypoints` is enabled.
+    pub entrypoint_stats: HashMap<(ClassHash, EntryPointSelector), EntrypointStats>,
+    // While frozen, no transaction is accepted and no block is produced.
+    pub frozen: bool,
+    pub snapshots: BTreeMap<u64, StarknetSnapshot>,
+    pub next_snapshot_id: u64,
+    // Added to the timestamp of every new block, to move the time of the chain.
+    pub time_offset: i64,
 }
 
 impl StarknetWrapper {
-    pub fn new(config: StarknetConfig) -> Self {
+    /// Creates the genesis state from the config, failing if the config is invalid, the predeployed
+    /// accounts can't be generated or the genesis allocations can't be loaded.
+    pub fn new(config: StarknetConfig) -> Result<Self> {
+        config.validate()?;
+
         let blocks = StarknetBlocks::default();
         let block_context = block_context_from_config(&config);
         let transactions = StarknetTransactions::default();
         let mut state = DictStateReader::default();
+        deploy_fee_contract(&mut state, config.fee_token_address);
+        deploy_universal_deployer_contract(&mut state, config.universal_deployer_address);
         let pending_state = CachedState::new(state.clone());
 
         let predeployed_accounts = PredeployedAccounts::initialize(
@@ -81,11 +170,19 @@ impl StarknetWrapper {
             config.seed,
             *DEFAULT_PREFUNDED_ACCOUNT_BALANCE,
             config.account_path.clone(),
-        )
-        .expect("should be able to generate accounts");
-        predeployed_accounts.deploy_accounts(&mut state);
+        )?;
+        predeployed_accounts.deploy_accounts(&mut state, config.fee_token_address);
+
+        if let Some(path) = &config.genesis_allocations {
+            let total = deploy_allocations(&mut state, config.fee_token_address, path)
+                .with_context(|| {
+                    format!("failed to load genesis allocations from {}", path.display())
+                })?;
+            info!("Loaded {total} genesis allocations from {}", path.display());
+        }
 
-        Self {
+        Ok(Self {
+            genesis_state: state.clone(),
             state,
             config,
             blocks,
@@ -93,7 +190,192 @@ impl StarknetWrapper {
             block_context,
             pending_state,
             predeployed_accounts,
+            class_abis: HashMap::new(),
+            compiled_casms: HashMap::new(),
+            block_building_hooks: Vec::new(),
+            inclusion_policies: Vec::new(),
+            pending_transactions: broadcast::channel(PENDING_TRANSACTIONS_CHANNEL_CAPACITY).0,
+            chain_notifications: broadcast::channel(CHAIN_NOTIFICATIONS_CHANNEL_CAPACITY).0,
+            settlement: MockSettlement::default(),
+            entrypoint_stats: HashMap::new(),
+            frozen: false,
+            snapshots: BTreeMap::new(),
+            next_snapshot_id: 0,
+            time_offset: 0,
+        })
+    }
+
+    pub fn add_block_building_hook(&mut self, hook: impl BlockBuildingHook + 'static) {
+        self.block_building_hooks.push(Box::new(hook));
+    }
+
+    pub fn subscribe_pending_transactions(
+        &self,
+    ) -> broadcast::Receiver<starknet_api::transaction::Transaction> {
+        self.pending_transactions.subscribe()
+    }
+
+    pub fn subscribe_chain_notifications(&self) -> broadcast::Receiver<ChainNotification> {
+        self.chain_notifications.subscribe()
+    }
+
+    pub fn add_inclusion_policy(&mut self, policy: impl InclusionPolicy + 'static) {
+        self.inclusion_policies.push(Box::new(policy));
+    }
+
+    /// Executes an L1 handler transaction for each message queued on the settlement layer, and
+    /// returns the hashes of the transactions.
+    pub fn process_messages_to_l2(&mut self) -> Result<Vec<TransactionHash>> {
+        let mut hashes = Vec::new();
+        for message in self.settlement.drain_messages_to_l2() {
+            let transaction = message.to_l1_handler_transaction(&self.block_context.chain_id)?;
+            hashes.push(transaction.transaction_hash);
+            self.handle_transaction(Transaction::L1HandlerTransaction(transaction))?;
+        }
+        Ok(hashes)
+    }
+
+    /// Takes a snapshot of the chain and returns its id. The pending block must be empty, as the
+    /// changes made by its transactions can't be captured.
+    pub fn take_snapshot(&mut self) -> Result<u64> {
+        if self
+            .blocks
+            .pending_block
+            .as_ref()
+            .map_or(false, |block| !block.transactions().is_empty())
+        {
+            return Err(anyhow!(
+                "the pending block must be empty to take a snapshot"
+            ));
         }
+
+        let id = self.next_snapshot_id;
+        self.next_snapshot_id += 1;
+
+        self.snapshots.insert(
+            id,
+            StarknetSnapshot {
+                total_blocks: self.blocks.total_blocks(),
+                transaction_hashes: self.transactions.transactions.keys().copied().collect(),
+                state: self.state.clone(),
+                block_context: self.block_context.clone(),
+                class_abis: self.class_abis.clone(),
+                compiled_casms: self.compiled_casms.clone(),
+                settlement: self.settlement.clone(),
+                entrypoint_stats: self.entrypoint_stats.clone(),
+                time_offset: self.time_offset,
+            },
+        );
+
+        Ok(id)
+    }
+
+    /// Reverts the chain to a snapshot, discarding it along with the snapshots taken after it.
+    /// Returns whether the snapshot exists.
+    pub fn revert_to_snapshot(&mut self, id: u64) -> bool {
+        let Some(snapshot) = self.snapshots.remove(&id) else {
+            return false;
+        };
+        self.snapshots.split_off(&id);
+
+        let removed_block = |number: usize| {
+            self.blocks
+                .by_number(BlockNumber(number as u64))
+                .map(|block| (block.block_hash(), block.block_number()))
+        };
+        let reorg = removed_block(snapshot.total_blocks)
+            .zip(removed_block(self.blocks.total_blocks().saturating_sub(1)))
+            .map(|(starting_block, ending_block)| ChainNotification::Reorg {
+                starting_block,
+                ending_block,
+            });
+
+        self.blocks.truncate(snapshot.total_blocks);
+        self.transactions
+            .transactions
+            .retain(|hash, _| snapshot.transaction_hashes.contains(hash));
+        self.state = snapshot.state;
+        // The state stored for the latest block may have been overridden since the snapshot.
+        if let Some(latest) = self.blocks.current_block_number() {
+            self.blocks.store_state(latest, self.state.clone());
+        }
+        self.block_context = snapshot.block_context;
+        self.class_abis = snapshot.class_abis;
+        self.compiled_casms = snapshot.compiled_casms;
+        self.settlement = snapshot.settlement;
+        self.entrypoint_stats = snapshot.entrypoint_stats;
+        self.time_offset = snapshot.time_offset;
+
+        self.generate_pending_block();
+
+        if let Some(reorg) = reorg {
+            // sending only fails when there are no subscribers
+            let _ = self.chain_notifications.send(reorg);
+        }
+
+        true
+    }
+
+    /// Applies a change directly to the state, bypassing transaction execution. The change is
+    /// visible in both the latest and the pending state but isn't part of the state diff of any
+    /// block, and a value written by a transaction of the pending block takes precedence over it.
+    ///
+    /// The change is also written to the stored state of the latest block, which the pending block
+    /// is replayed on top of.
+    pub fn override_state(&mut self, apply: impl Fn(&mut DictStateReader)) {
+        apply(&mut self.state);
+        apply(&mut self.pending_state.state);
+
+        let parent_state = match self.blocks.current_block_number() {
+            Some(latest) => self.blocks.get_state_mut(&latest),
+            None => Some(&mut self.genesis_state),
+        };
+        if let Some(parent_state) = parent_state {
+            apply(parent_state);
+        }
+    }
+
+    /// Moves the time of the chain forward by `seconds`, starting with the pending block. Fails if
+    /// the resulting timestamp is out of range.
+    pub fn increase_time(&mut self, seconds: u64) -> Result<()> {
+        let base_timestamp = self.base_block_timestamp(self.block_context.block_number);
+        let time_offset = i64::try_from(seconds)
+            .ok()
+            .and_then(|seconds| self.time_offset.checked_add(seconds))
+            .filter(|offset| {
+                i64::try_from(base_timestamp).map_or(false, |base_timestamp| {
+                    base_timestamp.checked_add(*offset).is_some()
+                })
+            })
+            .ok_or(anyhow!("increasing the time by {seconds}s is out of range"))?;
+
+        self.time_offset = time_offset;
+        self.set_pending_block_timestamp(self.block_timestamp(self.block_context.block_number));
+
+        Ok(())
+    }
+
+    /// Sets the timestamp of the pending block, the following blocks continuing from it. Fails if
+    /// it's earlier than the timestamp of the latest block, or out of range.
+    pub fn set_next_block_timestamp(&mut self, timestamp: u64) -> Result<()> {
+        let signed_timestamp = i64::try_from(timestamp)
+            .map_err(|_| anyhow!("timestamp {timestamp} is out of range"))?;
+
+        if let Some(latest) = self.blocks.latest() {
+            if timestamp < latest.header().timestamp.0 {
+                return Err(anyhow!(
+                    "timestamp {timestamp} is earlier than the latest block timestamp {}",
+                    latest.header().timestamp.0
+                ));
+            }
+        }
+
+        // Both are positive, so the difference can't overflow.
+        let base_timestamp = self.base_block_timestamp(self.block_context.block_number);
+        self.time_offset = signed_timestamp - base_timestamp as i64;
+        self.set_pending_block_timestamp(BlockTimestamp(timestamp));
+
+        Ok(())
     }
 
     pub fn state_from_block_id(&self, block_id: BlockId) -> Option<DictStateReader> {
@@ -132,8 +414,39 @@ impl StarknetWrapper {
         transaction.execute(&mut state, &self.block_context)
     }
 
+    // Simulate a sequence of transactions, each one on top of the changes made by the previous
+    // ones, without modifying the state. On failure, the index of the failing transaction is
+    // returned along with its error.
+    pub fn simulate_transactions(
+        &self,
+        transactions: Vec<AccountTransaction>,
+        state: Option<DictStateReader>,
+    ) -> Result<Vec<TransactionExecutionInfo>, (usize, TransactionExecutionError)> {
+        let mut state = CachedState::new(state.unwrap_or(self.pending_state()));
+        transactions
+            .into_iter()
+            .enumerate()
+            .map(|(index, transaction)| {
+                transaction
+                    .execute(&mut state, &self.block_context)
+                    .map_err(|err| (index, err))
+            })
+            .collect()
+    }
+
     // execute the tx
     pub fn handle_transaction(&mut self, transaction: Transaction) -> Result<()> {
+        if self.execute_transaction(transaction) && !self.config.blocks_on_demand {
+            self.generate_latest_block()?;
+            self.generate_pending_block();
+        }
+
+        Ok(())
+    }
+
+    // Execute the tx against the pending state and append it to the pending block if it succeeds.
+    // Returns whether the tx has been included in the pending block.
+    fn execute_transaction(&mut self, transaction: Transaction) -> bool {
         let api_tx = convert_blockifier_tx_to_starknet_api_tx(&transaction);
 
         info!(
@@ -141,6 +454,20 @@ impl StarknetWrapper {
             api_tx.transaction_hash()
         );
 
+        if let Some(reason) = self.exclusion_reason(&api_tx) {
+            warn!(
+                "Transaction excluded by inclusion policy | Transaction hash: {} | Reason: {reason}",
+                api_tx.transaction_hash()
+            );
+            self.store_transaction(StarknetTransaction::new(
+                api_tx,
+                TransactionStatus::Rejected,
+                None,
+                Some(reason),
+            ));
+            return false;
+        }
+
         let res = match transaction {
             Transaction::AccountTransaction(tx) => {
                 self.check_tx_fee(&tx);
@@ -160,19 +487,23 @@ impl StarknetWrapper {
                     None,
                 );
 
+                if self.config.trace_entrypoints {
+                    self.trace_entrypoints(&starknet_tx);
+                }
+
                 //  append successful tx to pending block
                 self.blocks
                     .pending_block
                     .as_mut()
                     .expect("no pending block")
-                    .insert_transaction(api_tx);
+                    .insert_transaction(api_tx.clone());
+
+                // sending only fails when there are no subscribers
+                let _ = self.pending_transactions.send(api_tx);
 
                 self.store_transaction(starknet_tx);
 
-                if !self.config.blocks_on_demand {
-                    self.generate_latest_block()?;
-                    self.generate_pending_block();
-                }
+                true
             }
 
             Err(exec_err) => {
@@ -180,14 +511,78 @@ impl StarknetWrapper {
                     api_tx,
                     TransactionStatus::Rejected,
                     None,
-                    Some(exec_err),
+                    Some(exec_err.to_string()),
                 );
 
                 self.store_transaction(tx);
+
+                false
             }
         }
+    }
 
-        Ok(())
+    fn trace_entrypoints(&mut self, transaction: &StarknetTransaction) {
+        let pending_state = &mut self.pending_state;
+        let resources = transaction
+            .entrypoint_resources(&mut |address| pending_state.get_class_hash_at(address).ok());
+
+        for resources in resources {
+            debug!(
+                target: "executor::entrypoint",
+                transaction_hash = %transaction.inner.transaction_hash().0,
+                class_hash = %resources.class_hash.0,
+                selector = %resources.selector.0,
+                steps = resources.steps,
+                memory_holes = resources.memory_holes,
+                builtins = ?resources.builtins,
+                "Entrypoint executed"
+            );
+
+            let stats = self
+                .entrypoint_stats
+                .entry((resources.class_hash, resources.selector))
+                .or_default();
+            stats.calls += 1;
+            stats.steps += resources.steps;
+            stats.memory_holes += resources.memory_holes;
+        }
+    }
+
+    // Returns the reason of the first inclusion policy excluding the tx, if any.
+    fn exclusion_reason(
+        &self,
+        transaction: &starknet_api::transaction::Transaction,
+    ) -> Option<String> {
+        let pending_block = self.blocks.pending_block.as_ref()?;
+
+        self.inclusion_policies
+            .iter()
+            .find(|policy| !policy.should_include(transaction, pending_block))
+            .map(|policy| policy.exclusion_reason())
+    }
+
+    // Execute the transactions injected by the block building hooks into the pending block
+    fn apply_block_building_hooks(&mut self) {
+        if self.blocks.pending_block.is_none() {
+            return;
+        }
+
+        let transactions = self
+            .block_building_hooks
+            .iter()
+            .filter_map(|hook| match hook.transactions(&self.block_context) {
+                Ok(transactions) => Some(transactions),
+                Err(err) => {
+                    warn!("Block building hook failed | Error: {err}");
+                    None
+                }
+            })
+            .flatten()
+            .collect::<Vec<_>>();
+
+        for transaction in transactions {
+            self.execute_transaction(transaction);
+        }
     }
 
     // Creates a new block that contains all the pending txs
@@ -195,6 +590,8 @@ impl StarknetWrapper {
     // Append the block to the chain
     // Update the block context
     pub fn generate_latest_block(&mut self) -> Result<StarknetBlock> {
+        self.apply_block_building_hooks();
+
         let mut new_block = if let Some(ref pending) = self.blocks.pending_block {
             pending.clone()
         } else {
@@ -213,6 +610,9 @@ impl StarknetWrapper {
                 tx.block_hash = Some(block_hash);
                 tx.status = TransactionStatus::AcceptedOnL2;
                 tx.block_number = Some(new_block.block_number());
+
+                self.settlement
+                    .add_messages_to_l1(new_block.block_number(), tx.l2_to_l1_messages());
             }
         }
 
@@ -254,9 +654,161 @@ impl StarknetWrapper {
 
         self.update_block_context();
 
+        // sending only fails when there are no subscribers
+        let _ = self
+            .chain_notifications
+            .send(ChainNotification::NewBlock(new_block.clone()));
+
         Ok(new_block)
     }
 
+    // Mines `num_blocks` blocks, the first one including the current pending transactions.
+    // If an `interval` is given, every following block is timestamped `interval` seconds after
+    // its parent instead of using the current time.
+    pub fn mine_blocks(&mut self, num_blocks: u64, interval: Option<u64>) -> Result<()> {
+        let mut parent_timestamp = None;
+
+        for _ in 0..num_blocks {
+            if let (Some(interval), Some(parent_timestamp)) = (interval, parent_timestamp) {
+                let timestamp = parent_timestamp.checked_add(interval).ok_or(anyhow!(
+                    "block timestamp overflows with an interval of {interval}s"
+                ))?;
+                // The following blocks continue from it, as with `dev_setNextBlockTimestamp`.
+                self.set_next_block_timestamp(timestamp)?;
+            }
+
+            let block = self.generate_latest_block()?;
+            self.generate_pending_block();
+
+            parent_timestamp = Some(block.header().timestamp.0);
+        }
+
+        Ok(())
+    }
+
+    // Aggregates the execution cost of the txs of a block by the class of the called contracts.
+    pub fn class_execution_stats(
+        &self,
+        block_id: BlockId,
+    ) -> Option<HashMap<ClassHash, ClassExecutionStats>> {
+        let block = match block_id {
+            BlockId::Tag(BlockTag::Pending) => self.blocks.pending_block.clone(),
+            id => self
+                .block_number_from_block_id(id)
+                .and_then(|number| self.blocks.by_number(number)),
+        }?;
+        let state = self.state_from_block_id(block_id)?;
+
+        let mut stats = HashMap::new();
+        block
+            .transactions()
+            .iter()
+            .filter_map(|tx| self.transactions.transactions.get(&tx.transaction_hash()))
+            .for_each(|tx| tx.add_class_execution_stats(&state, &mut stats));
+
+        Some(stats)
+    }
+
+    // Re-executes the transactions of a committed block on top of the state of its parent and
+    // returns whether the state they write matches the one stored for the block.
+    //
+    // Only the entries written by the transactions are compared, since the state can also be
+    // changed without transactions (e.g. by the predeployed accounts). The changes made by the dev
+    // cheatcodes before the block are part of the state of its parent, see `override_state`.
+    pub fn replay_block(&self, block_number: BlockNumber) -> Result<bool> {
+        let block = self
+            .blocks
+            .by_number(block_number)
+            .ok_or(anyhow!("block {block_number} not found"))?;
+        let expected_state = self
+            .state(block_number)
+            .ok_or(anyhow!("state of block {block_number} not found"))?;
+        let parent_state = match block_number.prev() {
+            Some(parent) => self
+                .state(parent)
+                .ok_or(anyhow!("state of block {parent} not found"))?,
+            None => self.genesis_state.clone(),
+        };
+
+        let mut block_context = self.block_context.clone();
+        block_context.block_number = block_number;
+        block_context.block_timestamp = block.header().timestamp;
+        block_context.gas_price = block.header().gas_price.0;
+
+        let mut state = CachedState::new(parent_state);
+
+        for transaction in block.transactions() {
+            let transaction_hash = transaction.transaction_hash();
+            let transaction = match transaction {
+                // The declared classes aren't kept in the txs, so they are taken from the state of
+                // the block.
+                starknet_api::transaction::Transaction::Declare(tx) => {
+                    let class_hash = match tx {
+                        starknet_api::transaction::DeclareTransaction::V0(tx)
+                        | starknet_api::transaction::DeclareTransaction::V1(tx) => tx.class_hash,
+                        starknet_api::transaction::DeclareTransaction::V2(tx) => tx.class_hash,
+                    };
+                    let contract_class = expected_state
+                        .class_hash_to_class
+                        .get(&class_hash)
+                        .cloned()
+                        .ok_or(anyhow!(
+                            "class {} declared by transaction {transaction_hash} not found",
+                            class_hash.0
+                        ))?;
+
+                    Transaction::AccountTransaction(AccountTransaction::Declare(
+                        DeclareTransaction {
+                            tx: tx.clone(),
+                            contract_class,
+                        },
+                    ))
+                }
+                transaction => convert_starknet_api_tx_to_blockifier_tx(transaction.clone())
+                    .ok_or(anyhow!("transaction {transaction_hash} can't be replayed"))?,
+            };
+
+            let exec_info = match transaction {
+                Transaction::AccountTransaction(tx) => tx.execute(&mut state, &block_context),
+                Transaction::L1HandlerTransaction(tx) => tx.execute(&mut state, &block_context),
+            }?;
+
+            info!(
+                "Transaction replayed | Transaction hash: {} | Actual fee: {}",
+                transaction_hash, exec_info.actual_fee.0
+            );
+        }
+
+        let state_diff = state.to_state_diff();
+
+        let storage_matches = state_diff.storage_updates.iter().all(|(address, entries)| {
+            entries.iter().all(|(key, value)| {
+                expected_state.storage_view.get(&(*address, *key)) == Some(value)
+            })
+        });
+        let nonces_match = state_diff
+            .address_to_nonce
+            .iter()
+            .all(|(address, nonce)| expected_state.address_to_nonce.get(address) == Some(nonce));
+        let class_hashes_match =
+            state_diff
+                .address_to_class_hash
+                .iter()
+                .all(|(address, class_hash)| {
+                    expected_state.address_to_class_hash.get(address) == Some(class_hash)
+                });
+        let compiled_class_hashes_match = state_diff.class_hash_to_compiled_class_hash.iter().all(
+            |(class_hash, compiled_class_hash)| {
+                expected_state
+                    .class_hash_to_compiled_class_hash
+                    .get(class_hash)
+                    == Some(compiled_class_hash)
+            },
+        );
+
+        Ok(storage_matches && nonces_match && class_hashes_match && compiled_class_hashes_match)
+    }
+
     pub fn generate_pending_block(&mut self) {
         self.blocks.pending_block = Some(self.create_new_empty_block());
         // Update the pending state to the latest committed state
@@ -297,6 +849,23 @@ impl StarknetWrapper {
         self.blocks.get_state(&block_number).cloned()
     }
 
+    /// The state update accumulated by the transactions of the pending block so far. As the block
+    /// isn't sealed yet, its hash and new root are zero.
+    pub fn pending_state_update(&self) -> StateUpdate {
+        StateUpdate {
+            block_hash: FieldElement::ZERO,
+            new_root: FieldElement::ZERO,
+            pending_state_update: PendingStateUpdate {
+                old_root: self.blocks.latest().map_or(FieldElement::ZERO, |block| {
+                    block.header().state_root.0.into()
+                }),
+                state_diff: convert_state_diff_to_rpc_state_diff(
+                    self.pending_state.to_state_diff(),
+                ),
+            },
+        }
+    }
+
     pub fn pending_state(&self) -> DictStateReader {
         let mut state = self.pending_state.state.clone();
         apply_state_diff(&mut state, self.pending_state.to_state_diff());
@@ -342,7 +911,7 @@ impl StarknetWrapper {
             GasPrice(self.block_context.gas_price),
             GlobalRoot(stark_felt!(0)),
             self.block_context.sequencer_address,
-            BlockTimestamp(get_current_timestamp().as_secs()),
+            self.block_timestamp(block_number),
             vec![],
             vec![],
             None,
@@ -359,9 +928,32 @@ impl StarknetWrapper {
             .insert(transaction.inner.transaction_hash(), transaction)
     }
 
+    fn set_pending_block_timestamp(&mut self, timestamp: BlockTimestamp) {
+        if let Some(block) = self.blocks.pending_block.as_mut() {
+            block.inner.header.timestamp = timestamp;
+        }
+        self.block_context.block_timestamp = timestamp;
+    }
+
     fn update_block_context(&mut self) {
         self.block_context.block_number = self.block_context.block_number.next();
-        self.block_context.block_timestamp = BlockTimestamp(get_current_timestamp().as_secs());
+        self.block_context.block_timestamp = self.block_timestamp(self.block_context.block_number);
+    }
+
+    fn block_timestamp(&self, block_number: BlockNumber) -> BlockTimestamp {
+        let timestamp =
+            (self.base_block_timestamp(block_number) as i64).saturating_add(self.time_offset);
+        BlockTimestamp(timestamp.max(0) as u64)
+    }
+
+    // In deterministic mode, block timestamps follow a fixed schedule instead of the system time so
+    // that the same inputs always produce the same blocks.
+    fn base_block_timestamp(&self, block_number: BlockNumber) -> u64 {
+        if self.config.deterministic {
+            DETERMINISTIC_GENESIS_TIMESTAMP + block_number.0 * DETERMINISTIC_BLOCK_TIME
+        } else {
+            get_current_timestamp().as_secs()
+        }
     }
 
     // apply the pending state diff to the state
@@ -0,0 +1,18 @@
+use starknet_api::block::{BlockHash, BlockNumber};
+
+use super::block::StarknetBlock;
+
+/// A change of the chain, sent to the subscribers of
+/// [`StarknetWrapper::subscribe_chain_notifications`](super::StarknetWrapper::subscribe_chain_notifications).
+#[derive(Debug, Clone)]
+pub enum ChainNotification {
+    /// A block has been sealed.
+    NewBlock(StarknetBlock),
+    /// Blocks have been removed from the chain by reverting to a snapshot.
+    Reorg {
+        /// The first removed block.
+        starting_block: (BlockHash, BlockNumber),
+        /// The last removed block.
+        ending_block: (BlockHash, BlockNumber),
+    },
+}
@@ -0,0 +1,48 @@
+use std::{collections::HashSet, fs, path::Path};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use starknet_api::{core::ClassHash, hash::StarkFelt};
+
+/// Which classes can be declared on the chain.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DeclarePolicy {
+    #[default]
+    AllowAll,
+    /// Only the listed classes can be declared.
+    Allowlist {
+        class_hashes: HashSet<ClassHash>,
+    },
+    DenyAll,
+}
+
+impl DeclarePolicy {
+    /// Reads an allowlist from a file with one class hash per line. Empty lines and lines
+    /// starting with `#` are ignored.
+    pub fn allowlist_from_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+
+        let class_hashes = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                StarkFelt::try_from(line)
+                    .map(ClassHash)
+                    .with_context(|| format!("invalid class hash {line}"))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self::Allowlist { class_hashes })
+    }
+
+    pub fn allows(&self, class_hash: &ClassHash) -> bool {
+        match self {
+            Self::AllowAll => true,
+            Self::Allowlist { class_hashes } => class_hashes.contains(class_hash),
+            Self::DenyAll => false,
+        }
+    }
+}
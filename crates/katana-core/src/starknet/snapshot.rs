@@ -0,0 +1,29 @@
+use std::collections::{HashMap, HashSet};
+
+use blockifier::block_context::BlockContext;
+use cairo_lang_starknet::casm_contract_class::CasmContractClass;
+use starknet_api::{
+    core::{ClassHash, EntryPointSelector},
+    transaction::TransactionHash,
+};
+
+use super::{messaging::MockSettlement, transaction::EntrypointStats};
+use crate::state::DictStateReader;
+
+/// The chain as it was when a snapshot was taken, used to revert to it.
+///
+/// Blocks and transactions are append-only, so only their number and hashes are kept; the blocks
+/// and transactions added after the snapshot are dropped when reverting. The node settings, such
+/// as the mining mode, whether the chain is frozen and the declare policy, aren't part of it.
+#[derive(Debug)]
+pub struct StarknetSnapshot {
+    pub total_blocks: usize,
+    pub transaction_hashes: HashSet<TransactionHash>,
+    pub state: DictStateReader,
+    pub block_context: BlockContext,
+    pub class_abis: HashMap<ClassHash, String>,
+    pub compiled_casms: HashMap<ClassHash, CasmContractClass>,
+    pub settlement: MockSettlement,
+    pub entrypoint_stats: HashMap<(ClassHash, EntryPointSelector), EntrypointStats>,
+    pub time_offset: i64,
+}
@@ -1,12 +1,12 @@
 use std::{collections::HashMap, vec};
 
-use blockifier::transaction::{
-    errors::TransactionExecutionError, objects::TransactionExecutionInfo,
+use blockifier::{
+    execution::entry_point::CallInfo, transaction::objects::TransactionExecutionInfo,
 };
 use starknet::core::types::TransactionStatus;
 use starknet_api::{
     block::{BlockHash, BlockNumber},
-    core::{ContractAddress, EntryPointSelector},
+    core::{ClassHash, ContractAddress, EntryPointSelector},
     hash::StarkFelt,
     stark_felt,
     transaction::{
@@ -16,12 +16,40 @@ use starknet_api::{
     },
 };
 
+use crate::state::DictStateReader;
+
 pub struct ExternalFunctionCall {
     pub calldata: Calldata,
     pub contract_address: ContractAddress,
     pub entry_point_selector: EntryPointSelector,
 }
 
+/// The execution cost of the calls made to the contracts of a class.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClassExecutionStats {
+    pub calls: u64,
+    /// The Cairo steps of the calls, excluding the steps of their inner calls.
+    pub steps: u64,
+}
+
+/// The resources used by a single entrypoint call, excluding the ones of its inner calls.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntrypointResources {
+    pub class_hash: ClassHash,
+    pub selector: EntryPointSelector,
+    pub steps: u64,
+    pub memory_holes: u64,
+    pub builtins: HashMap<String, u64>,
+}
+
+/// The accumulated cost of the calls made to an entrypoint.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EntrypointStats {
+    pub calls: u64,
+    pub steps: u64,
+    pub memory_holes: u64,
+}
+
 #[derive(Debug)]
 pub struct StarknetTransaction {
     pub inner: Transaction,
@@ -29,7 +57,9 @@ pub struct StarknetTransaction {
     pub block_hash: Option<BlockHash>,
     pub block_number: Option<BlockNumber>,
     pub execution_info: Option<TransactionExecutionInfo>,
-    pub execution_error: Option<TransactionExecutionError>,
+    // Why the tx was rejected, either its execution error or the reason given by the inclusion
+    // policy excluding it.
+    pub rejection_reason: Option<String>,
 }
 
 impl StarknetTransaction {
@@ -37,17 +67,17 @@ impl StarknetTransaction {
         inner: Transaction,
         status: TransactionStatus,
         execution_info: Option<TransactionExecutionInfo>,
-        execution_error: Option<TransactionExecutionError>,
+        rejection_reason: Option<String>,
     ) -> Self {
-        if status == TransactionStatus::Rejected && execution_error.is_none() {
-            panic!("rejected transaction must have an execution error");
+        if status == TransactionStatus::Rejected && rejection_reason.is_none() {
+            panic!("rejected transaction must have a rejection reason");
         };
 
         Self {
             inner,
             status,
             execution_info,
-            execution_error,
+            rejection_reason,
             block_hash: None,
             block_number: None,
         }
@@ -148,6 +178,51 @@ impl StarknetTransaction {
         messages
     }
 
+    // Adds the execution cost of the tx to `stats`, grouped by the class of the called contracts.
+    // `state` is used to resolve the class of the calls made by address.
+    pub fn add_class_execution_stats(
+        &self,
+        state: &DictStateReader,
+        stats: &mut HashMap<ClassHash, ClassExecutionStats>,
+    ) {
+        let Some(ref execution_info) = self.execution_info else {
+            return;
+        };
+
+        [
+            &execution_info.validate_call_info,
+            &execution_info.execute_call_info,
+            &execution_info.fee_transfer_call_info,
+        ]
+        .into_iter()
+        .flatten()
+        .for_each(|info| add_call_execution_stats(info, state, stats));
+    }
+
+    /// Returns the resources used by each entrypoint call of the transaction, in execution order.
+    /// `class_hash_at` resolves the class of the contracts called directly by their address.
+    pub fn entrypoint_resources(
+        &self,
+        class_hash_at: &mut dyn FnMut(ContractAddress) -> Option<ClassHash>,
+    ) -> Vec<EntrypointResources> {
+        let mut resources = Vec::new();
+
+        let Some(ref execution_info) = self.execution_info else {
+            return resources;
+        };
+
+        [
+            &execution_info.validate_call_info,
+            &execution_info.execute_call_info,
+            &execution_info.fee_transfer_call_info,
+        ]
+        .into_iter()
+        .flatten()
+        .for_each(|info| add_entrypoint_resources(info, class_hash_at, &mut resources));
+
+        resources
+    }
+
     fn output(&self) -> TransactionOutput {
         let actual_fee = self.actual_fee();
         let events = self.emitted_events();
@@ -194,4 +269,87 @@ impl StarknetTransactions {
     pub fn by_hash(&self, hash: &TransactionHash) -> Option<Transaction> {
         self.transactions.get(hash).map(|tx| tx.inner.clone())
     }
+
+    // Returns the reason why the tx was rejected, or `None` if it is unknown or wasn't rejected.
+    pub fn rejection_reason(&self, hash: &TransactionHash) -> Option<String> {
+        self.transactions
+            .get(hash)
+            .filter(|tx| tx.status == TransactionStatus::Rejected)
+            .and_then(|tx| tx.rejection_reason.clone())
+    }
+}
+
+fn add_call_execution_stats(
+    call_info: &CallInfo,
+    state: &DictStateReader,
+    stats: &mut HashMap<ClassHash, ClassExecutionStats>,
+) {
+    let class_hash = call_info.call.class_hash.or_else(|| {
+        state
+            .address_to_class_hash
+            .get(&call_info.call.storage_address)
+            .copied()
+    });
+
+    if let Some(class_hash) = class_hash {
+        // The resources of a call include the ones of its inner calls.
+        let inner_steps = call_info
+            .inner_calls
+            .iter()
+            .map(|call| call.vm_resources.n_steps)
+            .sum::<usize>();
+
+        let entry = stats.entry(class_hash).or_default();
+        entry.calls += 1;
+        entry.steps += call_info.vm_resources.n_steps.saturating_sub(inner_steps) as u64;
+    }
+
+    for inner_call in &call_info.inner_calls {
+        add_call_execution_stats(inner_call, state, stats);
+    }
+}
+
+fn add_entrypoint_resources(
+    call_info: &CallInfo,
+    class_hash_at: &mut dyn FnMut(ContractAddress) -> Option<ClassHash>,
+    resources: &mut Vec<EntrypointResources>,
+) {
+    let class_hash = call_info
+        .call
+        .class_hash
+        .or_else(|| class_hash_at(call_info.call.storage_address));
+
+    if let Some(class_hash) = class_hash {
+        // The resources of a call include the ones of its inner calls.
+        let mut steps = call_info.vm_resources.n_steps;
+        let mut memory_holes = call_info.vm_resources.n_memory_holes;
+        let mut builtins = call_info.vm_resources.builtin_instance_counter.clone();
+
+        for inner_call in &call_info.inner_calls {
+            steps = steps.saturating_sub(inner_call.vm_resources.n_steps);
+            memory_holes = memory_holes.saturating_sub(inner_call.vm_resources.n_memory_holes);
+
+            for (name, count) in &inner_call.vm_resources.builtin_instance_counter {
+                if let Some(total) = builtins.get_mut(name) {
+                    *total = total.saturating_sub(*count);
+                }
+            }
+        }
+
+        resources.push(EntrypointResources {
+            class_hash,
+            selector: call_info.call.entry_point_selector,
+            steps: steps as u64,
+            memory_holes: memory_holes as u64,
+            builtins: builtins
+                .into_iter()
+                .filter(|(_, count)| *count > 0)
+                .map(|(name, count)| (name, count as u64))
+                .collect(),
+        });
+    }
+
+    for inner_call in &call_info.inner_calls {
+        add_entrypoint_resources(inner_call, class_hash_at, resources);
+    }
 }
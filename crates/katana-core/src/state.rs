@@ -4,21 +4,18 @@ use blockifier::state::errors::StateError;
 use blockifier::state::state_api::StateReader;
 use blockifier::state::state_api::StateResult;
 use starknet_api::{
-    core::{ClassHash, CompiledClassHash, ContractAddress, Nonce, PatriciaKey},
-    hash::{StarkFelt, StarkHash},
-    patricia_key,
+    core::{ClassHash, CompiledClassHash, ContractAddress, Nonce},
+    hash::StarkFelt,
     state::StorageKey,
 };
 use std::collections::HashMap;
 
 use crate::constants::ERC20_CONTRACT;
 use crate::constants::ERC20_CONTRACT_CLASS_HASH;
-use crate::constants::FEE_TOKEN_ADDRESS;
-use crate::constants::UDC_ADDRESS;
 use crate::constants::UDC_CLASS_HASH;
 use crate::constants::UDC_CONTRACT;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct DictStateReader {
     pub storage_view: HashMap<ContractStorageKey, StarkFelt>,
     pub address_to_nonce: HashMap<ContractAddress, Nonce>,
@@ -27,21 +24,6 @@ pub struct DictStateReader {
     pub class_hash_to_compiled_class_hash: HashMap<ClassHash, CompiledClassHash>,
 }
 
-impl Default for DictStateReader {
-    fn default() -> Self {
-        let mut state = DictStateReader {
-            storage_view: HashMap::new(),
-            address_to_nonce: HashMap::new(),
-            address_to_class_hash: HashMap::new(),
-            class_hash_to_class: HashMap::new(),
-            class_hash_to_compiled_class_hash: HashMap::new(),
-        };
-        deploy_fee_contract(&mut state);
-        deploy_universal_deployer_contract(&mut state);
-        state
-    }
-}
-
 impl StateReader for DictStateReader {
     fn get_storage_at(
         &mut self,
@@ -99,24 +81,22 @@ impl StateReader for DictStateReader {
     }
 }
 
-fn deploy_fee_contract(state: &mut DictStateReader) {
+pub fn deploy_fee_contract(state: &mut DictStateReader, address: ContractAddress) {
     let erc20_class_hash = ClassHash(*ERC20_CONTRACT_CLASS_HASH);
     state
         .class_hash_to_class
         .insert(erc20_class_hash, (*ERC20_CONTRACT).clone());
-    state.address_to_class_hash.insert(
-        ContractAddress(patricia_key!(*FEE_TOKEN_ADDRESS)),
-        erc20_class_hash,
-    );
+    state
+        .address_to_class_hash
+        .insert(address, erc20_class_hash);
 }
 
-fn deploy_universal_deployer_contract(state: &mut DictStateReader) {
+pub fn deploy_universal_deployer_contract(state: &mut DictStateReader, address: ContractAddress) {
     let universal_deployer_class_hash = ClassHash(*UDC_CLASS_HASH);
     state
         .class_hash_to_class
         .insert(universal_deployer_class_hash, (*UDC_CONTRACT).clone());
-    state.address_to_class_hash.insert(
-        ContractAddress(patricia_key!(*UDC_ADDRESS)),
-        universal_deployer_class_hash,
-    );
+    state
+        .address_to_class_hash
+        .insert(address, universal_deployer_class_hash);
 }
@@ -15,14 +15,17 @@ use blockifier::{
     },
 };
 use starknet::{
-    core::types::{contract::legacy::LegacyContractClass, FieldElement},
+    core::types::{
+        contract::{legacy::LegacyContractClass, CompiledClass},
+        FieldElement,
+    },
     providers::jsonrpc::models::{
         ContractStorageDiffItem, DeclaredClassItem, DeployedContractItem, NonceUpdate, StateDiff,
         StorageEntry,
     },
 };
 use starknet_api::{
-    core::ClassHash,
+    core::{ClassHash, CompiledClassHash},
     hash::StarkFelt,
     transaction::{
         DeployAccountTransaction, InvokeTransaction, InvokeTransactionV1, L1HandlerTransaction,
@@ -130,6 +133,23 @@ pub fn convert_blockifier_tx_to_starknet_api_tx(
     }
 }
 
+// Declare transactions can't be converted back as the declared class is not part of the
+// starknet_api transaction.
+pub fn convert_starknet_api_tx_to_blockifier_tx(
+    transaction: Transaction,
+) -> Option<BlockifierTransaction> {
+    match transaction {
+        Transaction::Invoke(tx) => Some(BlockifierTransaction::AccountTransaction(
+            AccountTransaction::Invoke(tx),
+        )),
+        Transaction::DeployAccount(tx) => Some(BlockifierTransaction::AccountTransaction(
+            AccountTransaction::DeployAccount(tx),
+        )),
+        Transaction::L1Handler(tx) => Some(BlockifierTransaction::L1HandlerTransaction(tx)),
+        Transaction::Declare(_) | Transaction::Deploy(_) => None,
+    }
+}
+
 pub fn compute_legacy_class_hash(contract_class_str: &str) -> Result<ClassHash> {
     let contract_class: LegacyContractClass = ::serde_json::from_str(contract_class_str)?;
     let seirra_class_hash = contract_class.class_hash()?;
@@ -162,6 +182,24 @@ pub fn starkfelt_to_u128(felt: StarkFelt) -> Result<u128> {
 pub fn blockifier_contract_class_from_flattened_sierra_class(
     raw_contract_class: &str,
 ) -> Result<BlockifierContractClass> {
+    let casm_contract = casm_contract_class_from_flattened_sierra_class(raw_contract_class)?;
+    Ok(casm_contract.try_into()?)
+}
+
+pub fn compiled_class_hash_from_flattened_sierra_class(
+    raw_contract_class: &str,
+) -> Result<CompiledClassHash> {
+    let casm_contract = casm_contract_class_from_flattened_sierra_class(raw_contract_class)?;
+    let compiled_class =
+        serde_json::from_value::<CompiledClass>(serde_json::to_value(casm_contract)?)?;
+    Ok(CompiledClassHash(field_element_to_starkfelt(
+        &compiled_class.class_hash()?,
+    )))
+}
+
+pub fn casm_contract_class_from_flattened_sierra_class(
+    raw_contract_class: &str,
+) -> Result<CasmContractClass> {
     let value = serde_json::from_str::<serde_json::Value>(raw_contract_class)?;
     let contract_class = cairo_lang_starknet::contract_class::ContractClass {
         abi: serde_json::from_value(value["abi"].clone()).ok(),
@@ -175,7 +213,7 @@ pub fn blockifier_contract_class_from_flattened_sierra_class(
     };
 
     let casm_contract = CasmContractClass::from_contract_class(contract_class, true)?;
-    Ok(casm_contract.try_into()?)
+    Ok(casm_contract)
 }
 
 pub fn convert_state_diff_to_rpc_state_diff(state_diff: CommitmentStateDiff) -> StateDiff {
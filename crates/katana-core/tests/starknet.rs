@@ -1,33 +1,254 @@
+use std::path::PathBuf;
+
 use blockifier::abi::abi_utils::{get_storage_var_address, selector_from_name};
+use blockifier::block_context::BlockContext;
 use blockifier::transaction::{
     account_transaction::AccountTransaction, transaction_execution::Transaction,
 };
-use katana_core::constants::{DEFAULT_GAS_PRICE, FEE_TOKEN_ADDRESS, TEST_ACCOUNT_CONTRACT_PATH};
-use katana_core::starknet::{StarknetConfig, StarknetWrapper};
-use starknet::core::types::TransactionStatus;
+use katana_core::constants::{
+    DETERMINISTIC_BLOCK_TIME, DETERMINISTIC_GENESIS_TIMESTAMP, ERC20_CONTRACT_CLASS_HASH,
+    FEE_TOKEN_ADDRESS, TEST_ACCOUNT_CONTRACT_PATH, UDC_CLASS_HASH,
+};
+use katana_core::genesis::deploy_allocations;
+use katana_core::sequencer::{EstimateBundleError, KatanaSequencer, Sequencer};
+use katana_core::starknet::{
+    block::StarknetBlock,
+    event::{EventCursor, EventOrder},
+    hooks::{BlockBuildingHook, InclusionPolicy},
+    notification::ChainNotification,
+    policy::DeclarePolicy,
+    StarknetConfig, StarknetWrapper,
+};
+use katana_core::state::DictStateReader;
+use katana_core::util::get_current_timestamp;
+use starknet::core::types::{FieldElement, TransactionStatus};
+use starknet::providers::jsonrpc::models::{BlockId, BlockTag};
 use starknet_api::calldata;
+use starknet_api::core::{ClassHash, ContractAddress, EntryPointSelector, Nonce, PatriciaKey};
+use starknet_api::state::StorageKey;
 use starknet_api::transaction::InvokeTransaction;
 use starknet_api::{
     block::BlockNumber,
-    hash::StarkFelt,
-    stark_felt,
+    hash::{StarkFelt, StarkHash},
+    patricia_key, stark_felt,
     transaction::{Calldata, InvokeTransactionV1, TransactionHash},
 };
 
 fn create_test_starknet() -> StarknetWrapper {
+    StarknetWrapper::new(create_test_starknet_config()).unwrap()
+}
+
+// A path in the temporary directory unique to the process, so that concurrent runs don't collide.
+fn temp_file_path(name: &str, extension: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "katana_test_{name}_{}_{}.{extension}",
+        std::process::id(),
+        get_current_timestamp().as_nanos()
+    ))
+}
+
+fn create_test_starknet_config() -> StarknetConfig {
     let test_account_path = [env!("CARGO_MANIFEST_DIR"), TEST_ACCOUNT_CONTRACT_PATH]
         .iter()
         .collect();
 
-    StarknetWrapper::new(StarknetConfig {
-        seed: [0u8; 32],
+    StarknetConfig {
         total_accounts: 2,
-        blocks_on_demand: false,
         allow_zero_max_fee: true,
-        gas_price: DEFAULT_GAS_PRICE,
-        chain_id: String::from("KATANA"),
         account_path: Some(test_account_path),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_custom_genesis_addresses() {
+    let fee_token_address = ContractAddress(patricia_key!("0x1111"));
+    let universal_deployer_address = ContractAddress(patricia_key!("0x2222"));
+
+    let starknet = StarknetWrapper::new(StarknetConfig {
+        fee_token_address,
+        universal_deployer_address,
+        ..create_test_starknet_config()
     })
+    .unwrap();
+
+    assert_eq!(starknet.block_context.fee_token_address, fee_token_address);
+    assert_eq!(
+        starknet.state.address_to_class_hash.get(&fee_token_address),
+        Some(&ClassHash(*ERC20_CONTRACT_CLASS_HASH))
+    );
+    assert_eq!(
+        starknet
+            .state
+            .address_to_class_hash
+            .get(&universal_deployer_address),
+        Some(&ClassHash(*UDC_CLASS_HASH))
+    );
+    assert!(!starknet
+        .state
+        .address_to_class_hash
+        .contains_key(&ContractAddress(patricia_key!(*FEE_TOKEN_ADDRESS))));
+
+    let account = &starknet.predeployed_accounts.accounts[0];
+    let balance_key = (
+        fee_token_address,
+        get_storage_var_address("ERC20_balances", &[*account.account_address.0.key()]).unwrap(),
+    );
+    assert_eq!(
+        starknet.state.storage_view.get(&balance_key),
+        Some(&account.balance)
+    );
+}
+
+#[test]
+fn test_genesis_allocations() {
+    let path = temp_file_path("genesis_allocations", "csv");
+    std::fs::write(&path, "address,balance\n0x1234,0x64\n0x5678,1000\n").unwrap();
+
+    let starknet = StarknetWrapper::new(StarknetConfig {
+        genesis_allocations: Some(path.clone()),
+        ..create_test_starknet_config()
+    })
+    .unwrap();
+
+    for (address, balance) in [
+        ("0x1234", stark_felt!("0x64")),
+        ("0x5678", stark_felt!(1000u64)),
+    ] {
+        let balance_key = (
+            ContractAddress(patricia_key!(*FEE_TOKEN_ADDRESS)),
+            get_storage_var_address("ERC20_balances", &[stark_felt!(address)]).unwrap(),
+        );
+        assert_eq!(
+            starknet.state.storage_view.get(&balance_key),
+            Some(&balance)
+        );
+    }
+
+    // Neither invalid allocations nor allocations to the predeployed accounts are accepted.
+    let account = *starknet.predeployed_accounts.accounts[0]
+        .account_address
+        .0
+        .key();
+    for allocations in [
+        "0x1234,not a balance\n".to_string(),
+        format!("{account},0x1\n"),
+    ] {
+        std::fs::write(&path, allocations).unwrap();
+        assert!(StarknetWrapper::new(StarknetConfig {
+            genesis_allocations: Some(path.clone()),
+            ..create_test_starknet_config()
+        })
+        .is_err());
+    }
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_genesis_allocations_rejects_duplicates() {
+    let path = temp_file_path("genesis_allocations_duplicates", "json");
+    std::fs::write(
+        &path,
+        r#"[{ "address": "0x1234", "balance": "0x1" }, { "address": "4660", "balance": "0x2" }]"#,
+    )
+    .unwrap();
+
+    let mut state = DictStateReader::default();
+    let result = deploy_allocations(
+        &mut state,
+        ContractAddress(patricia_key!(*FEE_TOKEN_ADDRESS)),
+        &path,
+    );
+
+    assert!(
+        result.is_err(),
+        "the same address must not be allocated twice"
+    );
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_genesis_allocations_streamed_from_json() {
+    let path = temp_file_path("genesis_allocations_streamed", "json");
+    let allocations = (1..=1000u64)
+        .map(|i| format!(r#"{{ "address": "{:#x}", "balance": "{i}" }}"#, 0x1000 + i))
+        .collect::<Vec<_>>()
+        .join(",");
+    std::fs::write(&path, format!("[{allocations}]")).unwrap();
+
+    let mut state = DictStateReader::default();
+    let total = deploy_allocations(
+        &mut state,
+        ContractAddress(patricia_key!(*FEE_TOKEN_ADDRESS)),
+        &path,
+    )
+    .unwrap();
+    assert_eq!(total, 1000);
+
+    // An invalid allocation is reported even after valid ones were applied.
+    std::fs::write(
+        &path,
+        r#"[{ "address": "0x1234", "balance": "0x1" }, { "address": "0x5678" }]"#,
+    )
+    .unwrap();
+    assert!(deploy_allocations(
+        &mut DictStateReader::default(),
+        ContractAddress(patricia_key!(*FEE_TOKEN_ADDRESS)),
+        &path,
+    )
+    .is_err());
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_deterministic_mode_rejects_seed() {
+    let result = StarknetWrapper::new(StarknetConfig {
+        deterministic: true,
+        seed: [1u8; 32],
+        ..create_test_starknet_config()
+    });
+    assert!(
+        result.is_err(),
+        "a seed can't be given in deterministic mode"
+    );
+}
+
+#[test]
+fn test_deterministic_mode() {
+    let create_blocks = || {
+        let mut starknet = StarknetWrapper::new(StarknetConfig {
+            deterministic: true,
+            ..create_test_starknet_config()
+        })
+        .unwrap();
+        starknet.generate_pending_block();
+        starknet.mine_blocks(3, None).unwrap();
+        starknet
+    };
+
+    let first = create_blocks();
+    let second = create_blocks();
+
+    for number in 0..3 {
+        let block = first.blocks.by_number(BlockNumber(number)).unwrap();
+
+        assert_eq!(
+            block.header().timestamp.0,
+            DETERMINISTIC_GENESIS_TIMESTAMP + number * DETERMINISTIC_BLOCK_TIME
+        );
+        assert_eq!(
+            block.block_hash(),
+            second
+                .blocks
+                .by_number(BlockNumber(number))
+                .unwrap()
+                .block_hash(),
+            "blocks must be identical across runs"
+        );
+    }
 }
 
 #[test]
@@ -69,6 +290,47 @@ fn test_creating_blocks() {
     assert_eq!(last_block.block_number(), BlockNumber(2));
 }
 
+#[test]
+fn test_mine_blocks_with_interval() {
+    let mut starknet = create_test_starknet();
+    starknet.generate_pending_block();
+
+    starknet.mine_blocks(3, Some(100)).unwrap();
+
+    assert_eq!(starknet.blocks.total_blocks(), 3);
+    assert_eq!(starknet.block_context.block_number, BlockNumber(3));
+
+    let block0 = starknet.blocks.by_number(BlockNumber(0)).unwrap();
+    let block1 = starknet.blocks.by_number(BlockNumber(1)).unwrap();
+    let block2 = starknet.blocks.by_number(BlockNumber(2)).unwrap();
+
+    assert_eq!(
+        block1.header().timestamp.0,
+        block0.header().timestamp.0 + 100
+    );
+    assert_eq!(
+        block2.header().timestamp.0,
+        block1.header().timestamp.0 + 100
+    );
+
+    // The blocks mined afterwards don't go back in time.
+    starknet.mine_blocks(1, None).unwrap();
+    let timestamps = (0..4)
+        .map(|number| {
+            starknet
+                .blocks
+                .by_number(BlockNumber(number))
+                .unwrap()
+                .header()
+                .timestamp
+                .0
+        })
+        .collect::<Vec<_>>();
+    assert!(timestamps.windows(2).all(|pair| pair[0] <= pair[1]));
+
+    assert!(starknet.mine_blocks(2, Some(u64::MAX)).is_err());
+}
+
 #[test]
 fn test_add_transaction() {
     let mut starknet = create_test_starknet();
@@ -149,6 +411,941 @@ fn test_add_transaction() {
     // );
 }
 
+#[test]
+fn test_replay_block() {
+    let mut starknet = create_test_starknet();
+    starknet.generate_pending_block();
+
+    let a = starknet.predeployed_accounts.accounts[0].clone();
+    let b = starknet.predeployed_accounts.accounts[1].clone();
+
+    let execute_calldata = calldata![
+        *FEE_TOKEN_ADDRESS,               // Contract address.
+        selector_from_name("transfer").0, // EP selector.
+        stark_felt!(3),                   // Calldata length.
+        *b.account_address.0.key(),       // Calldata: recipient.
+        stark_felt!("0x99"),              // Calldata: amount low.
+        stark_felt!(0x0)                  // Calldata: amount high.
+    ];
+
+    starknet
+        .handle_transaction(Transaction::AccountTransaction(AccountTransaction::Invoke(
+            InvokeTransaction::V1(InvokeTransactionV1 {
+                sender_address: a.account_address,
+                calldata: execute_calldata,
+                transaction_hash: TransactionHash(stark_felt!("0x6969")),
+                ..Default::default()
+            }),
+        )))
+        .unwrap();
+
+    assert!(
+        starknet.replay_block(BlockNumber(0)).unwrap(),
+        "replayed state must match the stored state"
+    );
+}
+
+#[test]
+fn test_replay_blocks_ignores_writes_without_transactions() {
+    let mut sequencer = KatanaSequencer::new(create_test_starknet_config()).unwrap();
+    sequencer.start();
+
+    let a = sequencer.starknet.predeployed_accounts.accounts[0].clone();
+    let b = sequencer.starknet.predeployed_accounts.accounts[1].clone();
+
+    // Written to the state of the next block, but by no transaction.
+    sequencer
+        .set_storage_at(
+            ContractAddress(patricia_key!("0x1234")),
+            StorageKey(patricia_key!("0x5")),
+            stark_felt!("0x99"),
+        )
+        .unwrap();
+
+    sequencer
+        .starknet
+        .handle_transaction(Transaction::AccountTransaction(AccountTransaction::Invoke(
+            InvokeTransaction::V1(InvokeTransactionV1 {
+                sender_address: a.account_address,
+                calldata: calldata![
+                    *FEE_TOKEN_ADDRESS,
+                    selector_from_name("transfer").0,
+                    stark_felt!(3),
+                    *b.account_address.0.key(),
+                    stark_felt!("0x99"),
+                    stark_felt!(0x0)
+                ],
+                transaction_hash: TransactionHash(stark_felt!("0x6969")),
+                ..Default::default()
+            }),
+        )))
+        .unwrap();
+
+    let latest = sequencer.starknet.blocks.current_block_number().unwrap();
+    let results = sequencer.replay_blocks(BlockNumber(0), BlockNumber(latest.0 + 1));
+
+    let (missing, replayed) = results.split_last().unwrap();
+    assert!(replayed
+        .iter()
+        .all(|(_, result)| matches!(result, Ok(true))));
+
+    // A block which can't be replayed doesn't fail the others.
+    assert_eq!(missing.0, BlockNumber(latest.0 + 1));
+    assert!(missing.1.is_err());
+}
+
+#[test]
+fn test_replay_block_after_overriding_state() {
+    let mut sequencer = KatanaSequencer::new(create_test_starknet_config()).unwrap();
+    sequencer.start();
+
+    let a = sequencer.starknet.predeployed_accounts.accounts[0].account_address;
+    let b = sequencer.starknet.predeployed_accounts.accounts[1].account_address;
+
+    let transfer = |sender: ContractAddress, recipient: ContractAddress, hash: StarkFelt| {
+        Transaction::AccountTransaction(AccountTransaction::Invoke(InvokeTransaction::V1(
+            InvokeTransactionV1 {
+                sender_address: sender,
+                calldata: calldata![
+                    *FEE_TOKEN_ADDRESS,
+                    selector_from_name("transfer").0,
+                    stark_felt!(3),
+                    *recipient.0.key(),
+                    stark_felt!("0x99"),
+                    stark_felt!(0x0)
+                ],
+                transaction_hash: TransactionHash(hash),
+                ..Default::default()
+            },
+        )))
+    };
+
+    sequencer
+        .starknet
+        .handle_transaction(transfer(a, b, stark_felt!("0x6969")))
+        .unwrap();
+
+    // Read and written by the transaction of the next block.
+    sequencer.set_balance(b, 0x123456789).unwrap();
+
+    sequencer
+        .starknet
+        .handle_transaction(transfer(b, a, stark_felt!("0x6970")))
+        .unwrap();
+
+    let latest = sequencer.starknet.blocks.current_block_number().unwrap();
+    let transfer_block = sequencer
+        .starknet
+        .transactions
+        .transactions
+        .get(&TransactionHash(stark_felt!("0x6970")))
+        .and_then(|tx| tx.block_number)
+        .unwrap();
+    assert_eq!(transfer_block, latest);
+
+    assert!(
+        sequencer.starknet.replay_block(latest).unwrap(),
+        "replayed state must match the stored state"
+    );
+}
+
+struct TransferHook {
+    sender: ContractAddress,
+    recipient: ContractAddress,
+}
+
+impl BlockBuildingHook for TransferHook {
+    fn transactions(&self, _block_context: &BlockContext) -> anyhow::Result<Vec<Transaction>> {
+        let execute_calldata = calldata![
+            *FEE_TOKEN_ADDRESS,               // Contract address.
+            selector_from_name("transfer").0, // EP selector.
+            stark_felt!(3),                   // Calldata length.
+            *self.recipient.0.key(),          // Calldata: recipient.
+            stark_felt!("0x99"),              // Calldata: amount low.
+            stark_felt!(0x0)                  // Calldata: amount high.
+        ];
+
+        Ok(vec![Transaction::AccountTransaction(
+            AccountTransaction::Invoke(InvokeTransaction::V1(InvokeTransactionV1 {
+                sender_address: self.sender,
+                calldata: execute_calldata,
+                transaction_hash: TransactionHash(stark_felt!("0x4242")),
+                ..Default::default()
+            })),
+        )])
+    }
+}
+
+#[test]
+fn test_block_building_hook() {
+    let mut starknet = create_test_starknet();
+    starknet.generate_pending_block();
+
+    let a = starknet.predeployed_accounts.accounts[0].clone();
+    let b = starknet.predeployed_accounts.accounts[1].clone();
+
+    starknet.add_block_building_hook(TransferHook {
+        sender: a.account_address,
+        recipient: b.account_address,
+    });
+
+    starknet.generate_latest_block().unwrap();
+
+    let block = starknet.blocks.by_number(BlockNumber(0)).unwrap();
+
+    assert_eq!(
+        block.transaction_by_index(0).unwrap().transaction_hash(),
+        TransactionHash(stark_felt!("0x4242")),
+        "hook transaction must be included in the block"
+    );
+}
+
+struct SenderAllowlistPolicy {
+    allowed: Vec<ContractAddress>,
+}
+
+impl InclusionPolicy for SenderAllowlistPolicy {
+    fn should_include(
+        &self,
+        transaction: &starknet_api::transaction::Transaction,
+        _pending_block: &StarknetBlock,
+    ) -> bool {
+        match transaction {
+            starknet_api::transaction::Transaction::Invoke(InvokeTransaction::V1(tx)) => {
+                self.allowed.contains(&tx.sender_address)
+            }
+            _ => true,
+        }
+    }
+
+    fn exclusion_reason(&self) -> String {
+        "sender not allowed".to_string()
+    }
+}
+
+#[test]
+fn test_inclusion_policy() {
+    let mut starknet = create_test_starknet();
+    starknet.generate_pending_block();
+
+    let a = starknet.predeployed_accounts.accounts[0].clone();
+    let b = starknet.predeployed_accounts.accounts[1].clone();
+
+    starknet.add_inclusion_policy(SenderAllowlistPolicy {
+        allowed: vec![b.account_address],
+    });
+    starknet.add_block_building_hook(TransferHook {
+        sender: a.account_address,
+        recipient: b.account_address,
+    });
+
+    starknet.generate_latest_block().unwrap();
+
+    let block = starknet.blocks.by_number(BlockNumber(0)).unwrap();
+
+    assert!(
+        block.transactions().is_empty(),
+        "excluded transaction must not be included in the block"
+    );
+
+    let hash = TransactionHash(stark_felt!("0x4242"));
+    assert_eq!(
+        starknet.transactions.transactions[&hash].status,
+        TransactionStatus::Rejected
+    );
+    assert_eq!(
+        starknet.transactions.rejection_reason(&hash).as_deref(),
+        Some("sender not allowed")
+    );
+}
+
+#[test]
+fn test_class_execution_stats() {
+    let mut starknet = create_test_starknet();
+    starknet.generate_pending_block();
+
+    let a = starknet.predeployed_accounts.accounts[0].clone();
+    let b = starknet.predeployed_accounts.accounts[1].clone();
+
+    starknet.add_block_building_hook(TransferHook {
+        sender: a.account_address,
+        recipient: b.account_address,
+    });
+
+    starknet.generate_latest_block().unwrap();
+
+    let stats = starknet
+        .class_execution_stats(BlockId::Number(0))
+        .expect("block must exist");
+
+    let erc20_stats = stats
+        .get(&ClassHash(*ERC20_CONTRACT_CLASS_HASH))
+        .expect("the fee token must have been called");
+    assert!(erc20_stats.calls > 0);
+    assert!(
+        stats.contains_key(&a.class_hash),
+        "the account must have been called"
+    );
+}
+
+#[test]
+fn test_trace_entrypoints() {
+    let mut starknet = StarknetWrapper::new(StarknetConfig {
+        trace_entrypoints: true,
+        ..create_test_starknet_config()
+    })
+    .unwrap();
+    starknet.generate_pending_block();
+
+    let a = starknet.predeployed_accounts.accounts[0].clone();
+    let b = starknet.predeployed_accounts.accounts[1].clone();
+
+    starknet.add_block_building_hook(TransferHook {
+        sender: a.account_address,
+        recipient: b.account_address,
+    });
+
+    starknet.generate_latest_block().unwrap();
+
+    let transfer_stats = starknet
+        .entrypoint_stats
+        .get(&(
+            ClassHash(*ERC20_CONTRACT_CLASS_HASH),
+            selector_from_name("transfer"),
+        ))
+        .expect("the transfer entrypoint must have been traced");
+    assert!(transfer_stats.calls > 0);
+    assert!(transfer_stats.steps > 0);
+    assert!(starknet
+        .entrypoint_stats
+        .keys()
+        .any(|(class_hash, _)| *class_hash == a.class_hash));
+}
+
+#[test]
+fn test_pending_transactions_subscription() {
+    let mut starknet = create_test_starknet();
+    starknet.generate_pending_block();
+
+    let mut receiver = starknet.subscribe_pending_transactions();
+
+    let a = starknet.predeployed_accounts.accounts[0].clone();
+    let b = starknet.predeployed_accounts.accounts[1].clone();
+
+    starknet.add_block_building_hook(TransferHook {
+        sender: a.account_address,
+        recipient: b.account_address,
+    });
+
+    starknet.generate_latest_block().unwrap();
+
+    assert_eq!(
+        receiver.try_recv().unwrap().transaction_hash(),
+        TransactionHash(stark_felt!("0x4242")),
+        "subscribers must be notified of the pending transaction"
+    );
+}
+
+#[test]
+fn test_events_page_descending() {
+    let mut starknet = create_test_starknet();
+    starknet.generate_pending_block();
+
+    let a = starknet.predeployed_accounts.accounts[0].clone();
+    let b = starknet.predeployed_accounts.accounts[1].clone();
+
+    // Each transfer is included in its own block.
+    for i in 0..3u64 {
+        starknet
+            .handle_transaction(Transaction::AccountTransaction(AccountTransaction::Invoke(
+                InvokeTransaction::V1(InvokeTransactionV1 {
+                    sender_address: a.account_address,
+                    calldata: calldata![
+                        *FEE_TOKEN_ADDRESS,
+                        selector_from_name("transfer").0,
+                        stark_felt!(3),
+                        *b.account_address.0.key(),
+                        stark_felt!(i + 1),
+                        stark_felt!(0x0)
+                    ],
+                    transaction_hash: TransactionHash(stark_felt!(0x100 + i)),
+                    nonce: Nonce(stark_felt!(i)),
+                    ..Default::default()
+                }),
+            )))
+            .unwrap();
+    }
+
+    let to_block = starknet.blocks.current_block_number().unwrap();
+    let sequencer = KatanaSequencer { starknet };
+
+    let collect = |order: EventOrder| {
+        let mut events = Vec::new();
+        let mut cursor = None;
+        loop {
+            let (page, next) = sequencer
+                .events_page(BlockNumber(0), to_block, None, None, order, cursor, 2)
+                .unwrap();
+            assert!(page.len() <= 2);
+            events.extend(
+                page.into_iter()
+                    .map(|event| (event.transaction_hash, event.inner.content.data.0.clone())),
+            );
+            match next {
+                Some(next) => {
+                    assert_eq!(next.order, order);
+                    assert_eq!(next.to_string().parse::<EventCursor>().unwrap(), next);
+                    cursor = Some(next);
+                }
+                None => break,
+            }
+        }
+        events
+    };
+
+    let ascending = collect(EventOrder::Ascending);
+    let mut descending = collect(EventOrder::Descending);
+    assert!(ascending.len() >= 3);
+
+    descending.reverse();
+    assert_eq!(ascending, descending);
+
+    // A cursor created for one order can't be used for the other one.
+    let (_, cursor) = sequencer
+        .events_page(
+            BlockNumber(0),
+            to_block,
+            None,
+            None,
+            EventOrder::Descending,
+            None,
+            1,
+        )
+        .unwrap();
+    assert!(sequencer
+        .events_page(
+            BlockNumber(0),
+            to_block,
+            None,
+            None,
+            EventOrder::Ascending,
+            cursor,
+            1
+        )
+        .is_err());
+}
+
+#[test]
+fn test_mock_settlement_messages_to_l2() {
+    let mut starknet = create_test_starknet();
+    let to_address = ContractAddress(patricia_key!("0x100"));
+    let selector = EntryPointSelector(stark_felt!("0x200"));
+
+    let first = starknet.settlement.send_message_to_l2(
+        stark_felt!("0x1"),
+        to_address,
+        selector,
+        vec![stark_felt!(1), stark_felt!(2)],
+    );
+    let second =
+        starknet
+            .settlement
+            .send_message_to_l2(stark_felt!("0x1"), to_address, selector, vec![]);
+    assert_eq!(first, Nonce(stark_felt!(0)));
+    assert_eq!(second, Nonce(stark_felt!(1)));
+
+    let messages = starknet.settlement.drain_messages_to_l2();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(starknet.settlement.pending_messages_to_l2().count(), 0);
+
+    let chain_id = &starknet.block_context.chain_id;
+    let first = messages[0].to_l1_handler_transaction(chain_id).unwrap();
+    let second = messages[1].to_l1_handler_transaction(chain_id).unwrap();
+
+    // The L1 sender is prepended to the payload.
+    assert_eq!(
+        first.calldata.0.as_slice(),
+        &[stark_felt!("0x1"), stark_felt!(1), stark_felt!(2)]
+    );
+    assert_eq!(first.contract_address, to_address);
+    assert_eq!(first.entry_point_selector, selector);
+    assert_ne!(first.transaction_hash, second.transaction_hash);
+}
+
+#[test]
+fn test_pending_state_update() {
+    let mut starknet = StarknetWrapper::new(StarknetConfig {
+        blocks_on_demand: true,
+        ..create_test_starknet_config()
+    })
+    .unwrap();
+    starknet.generate_pending_block();
+
+    let a = starknet.predeployed_accounts.accounts[0].clone();
+    let b = starknet.predeployed_accounts.accounts[1].clone();
+
+    assert!(starknet
+        .pending_state_update()
+        .pending_state_update
+        .state_diff
+        .storage_diffs
+        .is_empty());
+
+    starknet
+        .handle_transaction(Transaction::AccountTransaction(AccountTransaction::Invoke(
+            InvokeTransaction::V1(InvokeTransactionV1 {
+                sender_address: a.account_address,
+                calldata: calldata![
+                    *FEE_TOKEN_ADDRESS,
+                    selector_from_name("transfer").0,
+                    stark_felt!(3),
+                    *b.account_address.0.key(),
+                    stark_felt!("0x99"),
+                    stark_felt!(0x0)
+                ],
+                transaction_hash: TransactionHash(stark_felt!("0x6969")),
+                ..Default::default()
+            }),
+        )))
+        .unwrap();
+
+    // The transfer is reflected before the block is sealed.
+    let state_update = starknet.pending_state_update();
+    assert_eq!(state_update.block_hash, FieldElement::ZERO);
+    assert!(state_update
+        .pending_state_update
+        .state_diff
+        .storage_diffs
+        .iter()
+        .any(|diff| diff.address == FieldElement::from(*FEE_TOKEN_ADDRESS)));
+
+    starknet.generate_latest_block().unwrap();
+    starknet.generate_pending_block();
+
+    assert!(starknet
+        .pending_state_update()
+        .pending_state_update
+        .state_diff
+        .storage_diffs
+        .is_empty());
+}
+
+#[test]
+fn test_frozen_chain_rejects_writes() {
+    let mut sequencer = KatanaSequencer::new(create_test_starknet_config()).unwrap();
+    sequencer.start();
+
+    sequencer.set_frozen(true);
+    assert!(sequencer.is_frozen());
+    assert!(sequencer.generate_new_block().is_err());
+    assert!(sequencer.mine_blocks(2, None).is_err());
+    assert_eq!(sequencer.starknet.blocks.total_blocks(), 0);
+
+    sequencer.set_frozen(false);
+    sequencer.generate_new_block().unwrap();
+    assert_eq!(sequencer.starknet.blocks.total_blocks(), 1);
+}
+
+#[test]
+fn test_declare_allowlist() {
+    let path = temp_file_path("declare_allowlist", "txt");
+    std::fs::write(&path, "# audited classes\n0x1234\n\n0x5678\n").unwrap();
+
+    let policy = DeclarePolicy::allowlist_from_file(&path).unwrap();
+    assert!(policy.allows(&ClassHash(stark_felt!("0x1234"))));
+    assert!(policy.allows(&ClassHash(stark_felt!("0x5678"))));
+    assert!(!policy.allows(&ClassHash(stark_felt!("0x9999"))));
+
+    assert!(DeclarePolicy::AllowAll.allows(&ClassHash(stark_felt!("0x9999"))));
+    assert!(!DeclarePolicy::DenyAll.allows(&ClassHash(stark_felt!("0x1234"))));
+
+    std::fs::write(&path, "0x1234\nnot a class hash\n").unwrap();
+    assert!(DeclarePolicy::allowlist_from_file(&path).is_err());
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_snapshot_and_revert() {
+    let mut starknet = create_test_starknet();
+    starknet.generate_pending_block();
+
+    let a = starknet.predeployed_accounts.accounts[0].clone();
+    let b = starknet.predeployed_accounts.accounts[1].clone();
+
+    let balance_key = (
+        ContractAddress(patricia_key!(*FEE_TOKEN_ADDRESS)),
+        get_storage_var_address("ERC20_balances", &[*b.account_address.0.key()]).unwrap(),
+    );
+    let balance_before = starknet.state.storage_view.get(&balance_key).copied();
+
+    let snapshot_id = starknet.take_snapshot().unwrap();
+    starknet.config.declare_policy = DeclarePolicy::DenyAll;
+
+    starknet
+        .handle_transaction(Transaction::AccountTransaction(AccountTransaction::Invoke(
+            InvokeTransaction::V1(InvokeTransactionV1 {
+                sender_address: a.account_address,
+                calldata: calldata![
+                    *FEE_TOKEN_ADDRESS,
+                    selector_from_name("transfer").0,
+                    stark_felt!(3),
+                    *b.account_address.0.key(),
+                    stark_felt!("0x99"),
+                    stark_felt!(0x0)
+                ],
+                transaction_hash: TransactionHash(stark_felt!("0x6969")),
+                ..Default::default()
+            }),
+        )))
+        .unwrap();
+
+    assert_eq!(starknet.blocks.total_blocks(), 1);
+    assert_ne!(
+        starknet.state.storage_view.get(&balance_key).copied(),
+        balance_before
+    );
+
+    assert!(starknet.revert_to_snapshot(snapshot_id));
+
+    assert_eq!(starknet.blocks.total_blocks(), 0);
+    assert_eq!(starknet.block_context.block_number, BlockNumber(0));
+    assert!(starknet
+        .transactions
+        .by_hash(&TransactionHash(stark_felt!("0x6969")))
+        .is_none());
+    assert_eq!(
+        starknet.state.storage_view.get(&balance_key).copied(),
+        balance_before
+    );
+    // The settings of the node aren't reverted.
+    assert!(matches!(
+        starknet.config.declare_policy,
+        DeclarePolicy::DenyAll
+    ));
+
+    // A snapshot can only be reverted to once.
+    assert!(!starknet.revert_to_snapshot(snapshot_id));
+}
+
+#[test]
+fn test_cheatcodes() {
+    let mut sequencer = KatanaSequencer::new(create_test_starknet_config()).unwrap();
+    sequencer.start();
+
+    let address = ContractAddress(patricia_key!("0x1234"));
+    let key = StorageKey(patricia_key!("0x5"));
+
+    sequencer
+        .set_storage_at(address, key, stark_felt!("0x99"))
+        .unwrap();
+    sequencer
+        .set_nonce(address, Nonce(stark_felt!("0x7")))
+        .unwrap();
+    sequencer
+        .set_class_hash_at(address, ClassHash(*ERC20_CONTRACT_CLASS_HASH))
+        .unwrap();
+    sequencer.set_balance(address, 1000).unwrap();
+
+    for block_id in [
+        BlockId::Tag(BlockTag::Latest),
+        BlockId::Tag(BlockTag::Pending),
+    ] {
+        assert_eq!(
+            sequencer.storage_at(address, key, block_id).unwrap(),
+            stark_felt!("0x99")
+        );
+        assert_eq!(
+            sequencer.nonce_at(block_id, address).unwrap(),
+            Nonce(stark_felt!("0x7"))
+        );
+        assert_eq!(
+            sequencer.class_hash_at(block_id, address).unwrap(),
+            ClassHash(*ERC20_CONTRACT_CLASS_HASH)
+        );
+        assert_eq!(
+            sequencer
+                .storage_at(
+                    ContractAddress(patricia_key!(*FEE_TOKEN_ADDRESS)),
+                    get_storage_var_address("ERC20_balances", &[*address.0.key()]).unwrap(),
+                    block_id,
+                )
+                .unwrap(),
+            stark_felt!(1000u64)
+        );
+    }
+
+    // Only declared classes can be set.
+    assert!(sequencer
+        .set_class_hash_at(address, ClassHash(stark_felt!("0xdead")))
+        .is_err());
+
+    // The changes are kept in the following blocks.
+    sequencer.generate_new_block().unwrap();
+    assert_eq!(
+        sequencer
+            .storage_at(address, key, BlockId::Tag(BlockTag::Latest))
+            .unwrap(),
+        stark_felt!("0x99")
+    );
+}
+
+#[test]
+fn test_time_manipulation() {
+    let mut starknet = StarknetWrapper::new(StarknetConfig {
+        deterministic: true,
+        ..create_test_starknet_config()
+    })
+    .unwrap();
+    starknet.generate_pending_block();
+
+    starknet.increase_time(100).unwrap();
+    starknet.mine_blocks(1, None).unwrap();
+    assert_eq!(
+        starknet
+            .blocks
+            .by_number(BlockNumber(0))
+            .unwrap()
+            .header()
+            .timestamp
+            .0,
+        DETERMINISTIC_GENESIS_TIMESTAMP + 100
+    );
+
+    // Blocks can't go back in time.
+    assert!(starknet
+        .set_next_block_timestamp(DETERMINISTIC_GENESIS_TIMESTAMP + 10)
+        .is_err());
+
+    starknet
+        .set_next_block_timestamp(DETERMINISTIC_GENESIS_TIMESTAMP + 1000)
+        .unwrap();
+    starknet.mine_blocks(2, None).unwrap();
+
+    assert_eq!(
+        starknet
+            .blocks
+            .by_number(BlockNumber(1))
+            .unwrap()
+            .header()
+            .timestamp
+            .0,
+        DETERMINISTIC_GENESIS_TIMESTAMP + 1000
+    );
+    assert_eq!(
+        starknet
+            .blocks
+            .by_number(BlockNumber(2))
+            .unwrap()
+            .header()
+            .timestamp
+            .0,
+        DETERMINISTIC_GENESIS_TIMESTAMP + 1000 + DETERMINISTIC_BLOCK_TIME
+    );
+
+    // Values which don't fit the time offset are rejected instead of wrapping around.
+    assert!(starknet.set_next_block_timestamp(u64::MAX).is_err());
+    assert!(starknet.increase_time(u64::MAX).is_err());
+    starknet.increase_time(i64::MAX as u64 / 2).unwrap();
+    assert!(starknet.increase_time(i64::MAX as u64 / 2 + 1).is_err());
+}
+
+#[test]
+fn test_toggle_auto_mining() {
+    let mut sequencer = KatanaSequencer::new(create_test_starknet_config()).unwrap();
+    sequencer.start();
+    sequencer.set_auto_mining(false).unwrap();
+    assert!(!sequencer.is_auto_mining());
+
+    let a = sequencer.starknet.predeployed_accounts.accounts[0].clone();
+    let b = sequencer.starknet.predeployed_accounts.accounts[1].clone();
+
+    sequencer
+        .starknet
+        .handle_transaction(Transaction::AccountTransaction(AccountTransaction::Invoke(
+            InvokeTransaction::V1(InvokeTransactionV1 {
+                sender_address: a.account_address,
+                calldata: calldata![
+                    *FEE_TOKEN_ADDRESS,
+                    selector_from_name("transfer").0,
+                    stark_felt!(3),
+                    *b.account_address.0.key(),
+                    stark_felt!("0x99"),
+                    stark_felt!(0x0)
+                ],
+                transaction_hash: TransactionHash(stark_felt!("0x6969")),
+                ..Default::default()
+            }),
+        )))
+        .unwrap();
+
+    assert_eq!(sequencer.starknet.blocks.total_blocks(), 0);
+
+    // Auto mining can't be enabled while the chain is frozen, as the pending block can't be mined.
+    sequencer.set_frozen(true);
+    assert!(sequencer.set_auto_mining(true).is_err());
+    assert!(!sequencer.is_auto_mining());
+    sequencer.set_frozen(false);
+
+    // The transaction waiting in the pending block is mined once auto mining is enabled.
+    sequencer.set_auto_mining(true).unwrap();
+    assert!(sequencer.is_auto_mining());
+    assert_eq!(sequencer.starknet.blocks.total_blocks(), 1);
+    assert_eq!(
+        sequencer
+            .starknet
+            .blocks
+            .by_number(BlockNumber(0))
+            .unwrap()
+            .transactions()
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn test_chain_notifications() {
+    let mut starknet = create_test_starknet();
+    starknet.generate_pending_block();
+    let mut receiver = starknet.subscribe_chain_notifications();
+
+    let snapshot_id = starknet.take_snapshot().unwrap();
+    starknet.mine_blocks(2, None).unwrap();
+
+    for number in 0..2 {
+        assert!(matches!(
+            receiver.try_recv().unwrap(),
+            ChainNotification::NewBlock(block) if block.block_number() == BlockNumber(number)
+        ));
+    }
+
+    let first_hash = starknet
+        .blocks
+        .by_number(BlockNumber(0))
+        .unwrap()
+        .block_hash();
+    let last_hash = starknet
+        .blocks
+        .by_number(BlockNumber(1))
+        .unwrap()
+        .block_hash();
+
+    assert!(starknet.revert_to_snapshot(snapshot_id));
+    assert!(matches!(
+        receiver.try_recv().unwrap(),
+        ChainNotification::Reorg { starting_block, ending_block }
+            if starting_block == (first_hash, BlockNumber(0))
+                && ending_block == (last_hash, BlockNumber(1))
+    ));
+}
+
+#[test]
+fn test_events_filtered_by_address() {
+    let mut starknet = create_test_starknet();
+    starknet.generate_pending_block();
+
+    let a = starknet.predeployed_accounts.accounts[0].clone();
+    let b = starknet.predeployed_accounts.accounts[1].clone();
+
+    starknet
+        .handle_transaction(Transaction::AccountTransaction(AccountTransaction::Invoke(
+            InvokeTransaction::V1(InvokeTransactionV1 {
+                sender_address: a.account_address,
+                calldata: calldata![
+                    *FEE_TOKEN_ADDRESS,
+                    selector_from_name("transfer").0,
+                    stark_felt!(3),
+                    *b.account_address.0.key(),
+                    stark_felt!(1),
+                    stark_felt!(0x0)
+                ],
+                transaction_hash: TransactionHash(stark_felt!(0x100)),
+                ..Default::default()
+            }),
+        )))
+        .unwrap();
+
+    let to_block = starknet.blocks.current_block_number().unwrap();
+    let sequencer = KatanaSequencer { starknet };
+
+    let events = |address: StarkFelt| {
+        sequencer
+            .events_page(
+                BlockNumber(0),
+                to_block,
+                Some(address),
+                None,
+                EventOrder::Ascending,
+                None,
+                100,
+            )
+            .unwrap()
+            .0
+    };
+
+    let fee_token_events = events(*FEE_TOKEN_ADDRESS);
+    assert!(!fee_token_events.is_empty());
+    assert!(fee_token_events
+        .iter()
+        .all(|event| *event.inner.from_address.0.key() == *FEE_TOKEN_ADDRESS));
+
+    assert!(events(stark_felt!("0xdead")).is_empty());
+}
+
+#[test]
+fn test_estimate_fee_bundle() {
+    let mut starknet = create_test_starknet();
+    starknet.generate_pending_block();
+
+    let a = starknet.predeployed_accounts.accounts[0].clone();
+    let b = starknet.predeployed_accounts.accounts[1].clone();
+    let sequencer = KatanaSequencer { starknet };
+
+    let transfer = |nonce: u64| {
+        AccountTransaction::Invoke(InvokeTransaction::V1(InvokeTransactionV1 {
+            sender_address: a.account_address,
+            nonce: Nonce(stark_felt!(nonce)),
+            calldata: calldata![
+                *FEE_TOKEN_ADDRESS,
+                selector_from_name("transfer").0,
+                stark_felt!(3),
+                *b.account_address.0.key(),
+                stark_felt!(1),
+                stark_felt!(0x0)
+            ],
+            ..Default::default()
+        }))
+    };
+
+    let estimates = sequencer
+        .estimate_fee_bundle(
+            vec![transfer(0), transfer(1)],
+            BlockId::Tag(BlockTag::Pending),
+        )
+        .unwrap();
+    assert_eq!(estimates.len(), 2);
+    assert!(estimates.iter().all(|estimate| estimate.overall_fee > 0));
+
+    // The second transaction is sent from an undeployed account.
+    let result = sequencer.estimate_fee_bundle(
+        vec![
+            transfer(0),
+            AccountTransaction::Invoke(InvokeTransaction::V1(Default::default())),
+            transfer(1),
+        ],
+        BlockId::Tag(BlockTag::Pending),
+    );
+    assert!(matches!(
+        result,
+        Err(EstimateBundleError::TransactionFailed { index: 1, .. })
+    ));
+
+    assert!(matches!(
+        sequencer.estimate_fee_bundle(vec![transfer(0)], BlockId::Number(100)),
+        Err(EstimateBundleError::BlockNotFound(_))
+    ));
+}
+
 #[test]
 fn test_add_reverted_transaction() {
     let mut starknet = create_test_starknet();
@@ -174,6 +1371,10 @@ fn test_add_reverted_transaction() {
     assert_eq!(tx.unwrap().block_hash, None);
     assert_eq!(tx.unwrap().block_number, None);
     assert_eq!(tx.unwrap().status, TransactionStatus::Rejected);
+    assert!(starknet
+        .transactions
+        .rejection_reason(&transaction_hash)
+        .is_some());
     assert_eq!(
         starknet.block_context.block_number,
         BlockNumber(0),
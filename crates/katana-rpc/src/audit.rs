@@ -0,0 +1,287 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+};
+
+use jsonrpsee::tracing::warn;
+use katana_core::util::get_current_timestamp;
+use serde::Serialize;
+use starknet::core::types::FieldElement;
+use tokio::task::futures::TaskLocalFuture;
+use tower::{Layer, Service};
+
+tokio::task_local! {
+    static CALLER: Arc<Mutex<Option<IpAddr>>>;
+}
+
+/// Sets the address of the client whose request is being served. Does nothing outside of a
+/// [`CallerService`].
+pub(crate) fn set_caller(addr: SocketAddr) {
+    let _ = CALLER.try_with(|caller| *caller.lock().unwrap() = Some(addr.ip()));
+}
+
+/// Returns the IP address of the client whose request is being served, if known.
+///
+/// The address is only known for the calls made over HTTP, since the calls made over a WebSocket
+/// connection are served outside of the [`CallerService`] of the upgrade request.
+pub(crate) fn caller_ip() -> Option<IpAddr> {
+    CALLER
+        .try_with(|caller| *caller.lock().unwrap())
+        .ok()
+        .flatten()
+}
+
+/// Makes the address of the client available to the methods through [`caller_ip`], once the
+/// server has reported it through [`set_caller`].
+///
+/// The methods can't read the extensions of the HTTP request, so the address is kept in a task
+/// local for the duration of the request instead.
+#[derive(Debug, Clone)]
+pub(crate) struct CallerLayer;
+
+impl<S> Layer<S> for CallerLayer {
+    type Service = CallerService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CallerService { inner }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct CallerService<S> {
+    inner: S,
+}
+
+impl<S, R> Service<R> for CallerService<S>
+where
+    S: Service<R>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = TaskLocalFuture<Arc<Mutex<Option<IpAddr>>>, S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        // The server may report the address either while the inner service is called or while
+        // its response is polled.
+        let caller = Arc::new(Mutex::new(None));
+        let response = CALLER.sync_scope(caller.clone(), || self.inner.call(request));
+        CALLER.scope(caller, response)
+    }
+}
+
+/// A JSON lines log recording every invocation of the RPC methods that modify the chain.
+///
+/// Only the method name, the IP address of the caller, the sender, the resulting transaction hash
+/// and whether the sequencer accepted the transaction are recorded, so signatures and calldata
+/// never end up in the log.
+#[derive(Debug)]
+pub struct AuditLog {
+    enabled: AtomicBool,
+    file: Option<Mutex<File>>,
+}
+
+#[derive(Serialize)]
+struct AuditEntry<'a> {
+    timestamp: u64,
+    method: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    caller_ip: Option<IpAddr>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sender_address: Option<FieldElement>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transaction_hash: Option<FieldElement>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    accepted: Option<bool>,
+}
+
+impl AuditLog {
+    /// Creates an audit log appending to the file at `path`, or one that records nothing if no
+    /// path is given.
+    pub fn new(path: Option<&Path>) -> io::Result<Self> {
+        let file = match path {
+            Some(path) => Some(Mutex::new(
+                OpenOptions::new().create(true).append(true).open(path)?,
+            )),
+            None => None,
+        };
+
+        Ok(Self {
+            enabled: AtomicBool::new(file.is_some()),
+            file,
+        })
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.file.is_some()
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn record(
+        &self,
+        method: &str,
+        caller_ip: Option<IpAddr>,
+        sender_address: Option<FieldElement>,
+        transaction_hash: Option<FieldElement>,
+    ) {
+        self.write(AuditEntry {
+            timestamp: get_current_timestamp().as_secs(),
+            method,
+            caller_ip,
+            sender_address,
+            transaction_hash,
+            accepted: None,
+        });
+    }
+
+    /// Records a submitted transaction, along with whether the sequencer accepted or rejected it.
+    pub fn record_transaction(
+        &self,
+        method: &str,
+        caller_ip: Option<IpAddr>,
+        sender_address: FieldElement,
+        transaction_hash: FieldElement,
+        accepted: bool,
+    ) {
+        self.write(AuditEntry {
+            timestamp: get_current_timestamp().as_secs(),
+            method,
+            caller_ip,
+            sender_address: Some(sender_address),
+            transaction_hash: Some(transaction_hash),
+            accepted: Some(accepted),
+        });
+    }
+
+    fn write(&self, entry: AuditEntry<'_>) {
+        let Some(file) = &self.file else {
+            return;
+        };
+
+        if !self.enabled.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let mut line = serde_json::to_string(&entry).expect("audit entry should serialize");
+        line.push('\n');
+
+        if let Err(err) = file.lock().unwrap().write_all(line.as_bytes()) {
+            warn!("failed to write audit log entry: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::Infallible, fs, future::Future, pin::Pin};
+
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    #[test]
+    fn test_audit_log() {
+        let path = std::env::temp_dir().join(format!(
+            "katana_audit_{}_{}.jsonl",
+            std::process::id(),
+            get_current_timestamp().as_nanos()
+        ));
+        let audit_log = AuditLog::new(Some(&path)).unwrap();
+        assert!(audit_log.is_configured());
+
+        audit_log.record("dev_mine", None, None, None);
+        audit_log.record_transaction(
+            "starknet_addInvokeTransaction",
+            Some(IpAddr::from([127, 0, 0, 1])),
+            FieldElement::ONE,
+            FieldElement::TWO,
+            false,
+        );
+        audit_log.set_enabled(false);
+        audit_log.record("dev_setBalance", None, None, None);
+        audit_log.set_enabled(true);
+        audit_log.record_transaction(
+            "starknet_addInvokeTransaction",
+            None,
+            FieldElement::ONE,
+            FieldElement::THREE,
+            true,
+        );
+
+        let entries = fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let mut entry: Value = serde_json::from_str(line).unwrap();
+                entry.as_object_mut().unwrap().remove("timestamp").unwrap();
+                entry
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            entries,
+            vec![
+                json!({ "method": "dev_mine" }),
+                json!({
+                    "method": "starknet_addInvokeTransaction",
+                    "caller_ip": "127.0.0.1",
+                    "sender_address": "0x1",
+                    "transaction_hash": "0x2",
+                    "accepted": false,
+                }),
+                json!({
+                    "method": "starknet_addInvokeTransaction",
+                    "sender_address": "0x1",
+                    "transaction_hash": "0x3",
+                    "accepted": true,
+                }),
+            ]
+        );
+
+        fs::remove_file(path).unwrap();
+        assert!(!AuditLog::new(None).unwrap().is_configured());
+    }
+
+    /// Reports the address of the client like the server does, and responds with the IP address
+    /// read back once polled.
+    struct ReportCaller;
+
+    impl Service<SocketAddr> for ReportCaller {
+        type Response = Option<IpAddr>;
+        type Error = Infallible;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, addr: SocketAddr) -> Self::Future {
+            set_caller(addr);
+            Box::pin(async { Ok(caller_ip()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_caller_layer() {
+        let addr = SocketAddr::from(([192, 168, 0, 2], 5050));
+
+        let ip = CallerLayer.layer(ReportCaller).call(addr).await.unwrap();
+        assert_eq!(ip, Some(addr.ip()));
+
+        assert_eq!(ReportCaller.call(addr).await.unwrap(), None);
+        assert_eq!(caller_ip(), None);
+    }
+}
@@ -1,4 +1,116 @@
+use std::{
+    fmt::Debug,
+    net::{IpAddr, Ipv4Addr},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+
+/// Replaces the log filter of the node at runtime.
+pub trait LogFilterReloader: Debug + Send + Sync {
+    /// Makes `directives`, in the `RUST_LOG` format, the new log filter.
+    fn reload(&self, directives: &str) -> Result<(), String>;
+}
+
 #[derive(Debug, Clone)]
 pub struct RpcConfig {
     pub port: u16,
+    pub hosts: Vec<IpAddr>,
+    pub audit_log: Option<PathBuf>,
+    pub slow_call_threshold: Option<Duration>,
+    /// Enables `dev_setLogFilter` when set.
+    pub log_filter: Option<Arc<dyn LogFilterReloader>>,
+    /// The port of the server-sent events endpoint, which is disabled if unset.
+    pub sse_port: Option<u16>,
+    /// How often the WebSocket connections are pinged, and a comment is sent on the idle
+    /// server-sent events streams, so that proxies don't close them.
+    pub keep_alive_interval: Duration,
+    /// The directory in which the calls served over HTTP are recorded, if any.
+    pub record_dir: Option<PathBuf>,
+}
+
+/// An invalid combination of options in an [`RpcConfig`].
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum RpcConfigError {
+    #[error("at least one host must be given")]
+    NoHosts,
+    #[error("the keep-alive interval must not be zero")]
+    ZeroKeepAliveInterval,
+    #[error("the server-sent events port must differ from the JSON-RPC port")]
+    SsePortConflict,
+}
+
+impl RpcConfig {
+    /// Checks that the options can be used together, as the CLI does when parsing them.
+    pub fn validate(&self) -> Result<(), RpcConfigError> {
+        if self.hosts.is_empty() {
+            return Err(RpcConfigError::NoHosts);
+        }
+        if self.keep_alive_interval.is_zero() {
+            return Err(RpcConfigError::ZeroKeepAliveInterval);
+        }
+        // A zero port picks a free port, so both servers can be given one.
+        if self.port != 0 && self.sse_port == Some(self.port) {
+            return Err(RpcConfigError::SsePortConflict);
+        }
+        Ok(())
+    }
+}
+
+impl Default for RpcConfig {
+    fn default() -> Self {
+        Self {
+            port: 5050,
+            hosts: vec![IpAddr::V4(Ipv4Addr::LOCALHOST)],
+            audit_log: None,
+            slow_call_threshold: None,
+            log_filter: None,
+            sse_port: None,
+            keep_alive_interval: Duration::from_secs(15),
+            record_dir: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(RpcConfig::default().validate(), Ok(()));
+        assert_eq!(
+            RpcConfig {
+                hosts: vec![],
+                ..Default::default()
+            }
+            .validate(),
+            Err(RpcConfigError::NoHosts)
+        );
+        assert_eq!(
+            RpcConfig {
+                keep_alive_interval: Duration::ZERO,
+                ..Default::default()
+            }
+            .validate(),
+            Err(RpcConfigError::ZeroKeepAliveInterval)
+        );
+        assert_eq!(
+            RpcConfig {
+                sse_port: Some(5050),
+                ..Default::default()
+            }
+            .validate(),
+            Err(RpcConfigError::SsePortConflict)
+        );
+        assert_eq!(
+            RpcConfig {
+                port: 0,
+                sse_port: Some(0),
+                ..Default::default()
+            }
+            .validate(),
+            Ok(())
+        );
+    }
 }
@@ -0,0 +1,260 @@
+use jsonrpsee::{
+    core::Error,
+    proc_macros::rpc,
+    types::{error::CallError, ErrorObject},
+};
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{contract::FlattenedSierraClass, FieldElement};
+
+use crate::discover::MethodSpec;
+
+#[derive(thiserror::Error, Clone, Copy, Debug)]
+pub enum DevApiError {
+    #[error("No predeployed account available")]
+    NoPredeployedAccount = 200,
+    #[error("Transaction rejected")]
+    TransactionRejected = 201,
+    #[error("Entrypoint tracing is not enabled")]
+    EntrypointTracingDisabled = 202,
+    #[error("The pending block must be empty to take a snapshot")]
+    PendingBlockNotEmpty = 203,
+    #[error("Log filter reloading is not available")]
+    LogFilterReloadUnavailable = 204,
+    #[error("Invalid log filter")]
+    InvalidLogFilter = 205,
+    #[error("The timestamp is earlier than the timestamp of the latest block")]
+    TimestampBeforeLatestBlock = 206,
+    #[error("The timestamp is out of range")]
+    TimestampOutOfRange = 207,
+    #[error("The class of the predeployed accounts is not supported")]
+    UnsupportedAccountClass = 208,
+}
+
+impl From<DevApiError> for Error {
+    fn from(err: DevApiError) -> Self {
+        Error::Call(CallError::Custom(ErrorObject::owned(
+            err as i32,
+            err.to_string(),
+            None::<()>,
+        )))
+    }
+}
+
+/// The outcome of re-executing a committed block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockReplayResult {
+    pub block_number: u64,
+    /// Whether the state resulting from the replay matches the stored state of the block.
+    pub state_matches: bool,
+    /// Why the block couldn't be replayed, if it couldn't.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A contract deployed with `dev_deployContract`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeployedContract {
+    pub contract_address: FieldElement,
+    pub class_hash: FieldElement,
+}
+
+/// A message sent to L1 by a transaction of a sealed block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageToL1Entry {
+    pub block_number: u64,
+    pub from_address: FieldElement,
+    pub to_address: FieldElement,
+    pub payload: Vec<FieldElement>,
+}
+
+/// The accumulated cost of the calls made to an entrypoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntrypointStatsEntry {
+    pub class_hash: FieldElement,
+    pub selector: FieldElement,
+    pub calls: u64,
+    /// The Cairo steps of the calls, excluding the steps of their inner calls.
+    pub steps: u64,
+    pub memory_holes: u64,
+}
+
+#[rpc(server, client, namespace = "dev")]
+pub trait DevApi {
+    #[method(name = "mine")]
+    async fn mine(&self, num_blocks: u64, interval: Option<u64>) -> Result<(), Error>;
+
+    #[method(name = "replayBlocks")]
+    async fn replay_blocks(
+        &self,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<BlockReplayResult>, Error>;
+
+    #[method(name = "deployContract")]
+    async fn deploy_contract(
+        &self,
+        contract_class: FlattenedSierraClass,
+        constructor_calldata: Vec<FieldElement>,
+        salt: FieldElement,
+    ) -> Result<DeployedContract, Error>;
+
+    /// Sends a message from L1 through the mock settlement layer, and returns the hash of the L1
+    /// handler transaction consuming it.
+    #[method(name = "sendMessageToL2")]
+    async fn send_message_to_l2(
+        &self,
+        from_address: FieldElement,
+        to_address: FieldElement,
+        selector: FieldElement,
+        payload: Vec<FieldElement>,
+    ) -> Result<FieldElement, Error>;
+
+    #[method(name = "getMessagesToL1")]
+    async fn messages_to_l1(&self) -> Result<Vec<MessageToL1Entry>, Error>;
+
+    #[method(name = "getEntrypointStats")]
+    async fn entrypoint_stats(&self) -> Result<Vec<EntrypointStatsEntry>, Error>;
+
+    #[method(name = "setStorageAt")]
+    async fn set_storage_at(
+        &self,
+        contract_address: FieldElement,
+        key: FieldElement,
+        value: FieldElement,
+    ) -> Result<(), Error>;
+
+    #[method(name = "setNonce")]
+    async fn set_nonce(
+        &self,
+        contract_address: FieldElement,
+        nonce: FieldElement,
+    ) -> Result<(), Error>;
+
+    /// Sets the fee token balance of a contract, which must fit in 128 bits.
+    #[method(name = "setBalance")]
+    async fn set_balance(
+        &self,
+        contract_address: FieldElement,
+        balance: FieldElement,
+    ) -> Result<(), Error>;
+
+    /// Replaces the class of the contract at an address with an already declared class.
+    #[method(name = "setCode")]
+    async fn set_code(
+        &self,
+        contract_address: FieldElement,
+        class_hash: FieldElement,
+    ) -> Result<(), Error>;
+
+    /// Enables or disables mining a block for every transaction. Enabling it stops interval
+    /// mining and mines the transactions waiting in the pending block.
+    #[method(name = "setAutomine")]
+    async fn set_automine(&self, enabled: bool) -> Result<(), Error>;
+
+    /// Mines a block every `interval` milliseconds instead of one per transaction, or stops
+    /// interval mining if `interval` is zero. Automine is disabled when interval mining starts,
+    /// and isn't enabled again when it stops.
+    #[method(name = "setIntervalMining")]
+    async fn set_interval_mining(&self, interval: u64) -> Result<(), Error>;
+
+    /// Moves the time of the chain forward by `seconds`, starting with the pending block.
+    #[method(name = "increaseTime")]
+    async fn increase_time(&self, seconds: u64) -> Result<(), Error>;
+
+    /// Sets the timestamp of the pending block, the following blocks continuing from it.
+    #[method(name = "setNextBlockTimestamp")]
+    async fn set_next_block_timestamp(&self, timestamp: u64) -> Result<(), Error>;
+
+    /// Replaces the log filter of the node, e.g. `katana_core=trace,info`.
+    #[method(name = "setLogFilter")]
+    async fn set_log_filter(&self, filter: String) -> Result<(), Error>;
+
+    /// Takes a snapshot of the chain, which can only be taken while the pending block is empty.
+    ///
+    /// Reverting to it restores the blocks, the transactions, the state, the declared classes, the
+    /// mock settlement messages, the entrypoint stats and the time offset. The node settings are
+    /// left as they are when reverting: the mining mode (automine and interval mining), whether
+    /// the chain is frozen and the declare policy.
+    #[method(name = "snapshot")]
+    async fn snapshot(&self) -> Result<u64, Error>;
+
+    /// Reverts the chain to a snapshot, which can't be used again afterwards, nor can the
+    /// snapshots taken after it. Returns whether the snapshot exists.
+    #[method(name = "revert")]
+    async fn revert(&self, snapshot_id: u64) -> Result<bool, Error>;
+}
+
+/// The signatures of the methods of [`DevApi`], for the OpenRPC document.
+pub(crate) const METHODS: &[MethodSpec] = &[
+    MethodSpec::new(
+        "dev_mine",
+        &[("num_blocks", "u64"), ("interval", "Option<u64>")],
+        "()",
+    ),
+    MethodSpec::new(
+        "dev_replayBlocks",
+        &[("from_block", "u64"), ("to_block", "u64")],
+        "Vec<BlockReplayResult>",
+    ),
+    MethodSpec::new(
+        "dev_deployContract",
+        &[
+            ("contract_class", "FlattenedSierraClass"),
+            ("constructor_calldata", "Vec<FieldElement>"),
+            ("salt", "FieldElement"),
+        ],
+        "DeployedContract",
+    ),
+    MethodSpec::new(
+        "dev_sendMessageToL2",
+        &[
+            ("from_address", "FieldElement"),
+            ("to_address", "FieldElement"),
+            ("selector", "FieldElement"),
+            ("payload", "Vec<FieldElement>"),
+        ],
+        "FieldElement",
+    ),
+    MethodSpec::new("dev_getMessagesToL1", &[], "Vec<MessageToL1Entry>"),
+    MethodSpec::new("dev_getEntrypointStats", &[], "Vec<EntrypointStatsEntry>"),
+    MethodSpec::new(
+        "dev_setStorageAt",
+        &[
+            ("contract_address", "FieldElement"),
+            ("key", "FieldElement"),
+            ("value", "FieldElement"),
+        ],
+        "()",
+    ),
+    MethodSpec::new(
+        "dev_setNonce",
+        &[
+            ("contract_address", "FieldElement"),
+            ("nonce", "FieldElement"),
+        ],
+        "()",
+    ),
+    MethodSpec::new(
+        "dev_setBalance",
+        &[
+            ("contract_address", "FieldElement"),
+            ("balance", "FieldElement"),
+        ],
+        "()",
+    ),
+    MethodSpec::new(
+        "dev_setCode",
+        &[
+            ("contract_address", "FieldElement"),
+            ("class_hash", "FieldElement"),
+        ],
+        "()",
+    ),
+    MethodSpec::new("dev_setAutomine", &[("enabled", "bool")], "()"),
+    MethodSpec::new("dev_setIntervalMining", &[("interval", "u64")], "()"),
+    MethodSpec::new("dev_increaseTime", &[("seconds", "u64")], "()"),
+    MethodSpec::new("dev_setNextBlockTimestamp", &[("timestamp", "u64")], "()"),
+    MethodSpec::new("dev_setLogFilter", &[("filter", "String")], "()"),
+    MethodSpec::new("dev_snapshot", &[], "u64"),
+    MethodSpec::new("dev_revert", &[("snapshot_id", "u64")], "bool"),
+];
@@ -0,0 +1,662 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use blockifier::{
+    execution::contract_class::{ContractClass, ContractClassV1},
+    transaction::{account_transaction::AccountTransaction, transactions::DeclareTransaction},
+};
+use jsonrpsee::{
+    core::{async_trait, Error},
+    tracing::warn,
+    types::{error::CallError, ErrorObject},
+};
+use katana_core::{
+    constants::DEFAULT_ACCOUNT_CONTRACT_CLASS_HASH,
+    sequencer::Sequencer,
+    util::{
+        casm_contract_class_from_flattened_sierra_class,
+        compiled_class_hash_from_flattened_sierra_class, starkfelt_to_u128,
+    },
+};
+use starknet::{
+    core::{
+        types::{contract::FlattenedSierraClass, FieldElement},
+        utils::get_selector_from_name,
+    },
+    providers::jsonrpc::models::{BlockId, BlockTag},
+    signers::SigningKey,
+};
+use starknet_api::{
+    block::BlockNumber,
+    core::{
+        calculate_contract_address, ClassHash, ContractAddress, EntryPointSelector, Nonce,
+        PatriciaKey,
+    },
+    hash::{StarkFelt, StarkHash},
+    patricia_key,
+    state::StorageKey,
+    transaction::{
+        Calldata, ContractAddressSalt, DeclareTransactionV2, Fee, InvokeTransaction,
+        InvokeTransactionV1, TransactionHash, TransactionSignature,
+    },
+};
+use tokio::{sync::RwLock, task::JoinHandle};
+
+use self::api::{
+    BlockReplayResult, DeployedContract, DevApiError, DevApiServer, EntrypointStatsEntry,
+    MessageToL1Entry,
+};
+use crate::{
+    audit::{caller_ip, AuditLog},
+    config::LogFilterReloader,
+    katana::{declare_error, ensure_not_frozen},
+    starknet::api::StarknetApiError,
+    utils::transaction::{compute_declare_v2_transaction_hash, compute_invoke_v1_transaction_hash},
+};
+
+pub mod api;
+
+/// The max fee of the transactions sent by `dev_deployContract`.
+const DEPLOY_CONTRACT_MAX_FEE: u64 = 10u64.pow(18);
+
+pub struct DevRpc<S> {
+    sequencer: Arc<RwLock<S>>,
+    audit_log: Arc<AuditLog>,
+    log_filter: Option<Arc<dyn LogFilterReloader>>,
+    // The task mining blocks at a fixed interval, if enabled.
+    interval_mining: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl<S: Sequencer + Send + Sync + 'static> DevRpc<S> {
+    pub fn new(
+        sequencer: Arc<RwLock<S>>,
+        audit_log: Arc<AuditLog>,
+        log_filter: Option<Arc<dyn LogFilterReloader>>,
+    ) -> Self {
+        Self {
+            sequencer,
+            audit_log,
+            log_filter,
+            interval_mining: Mutex::new(None),
+        }
+    }
+
+    fn stop_interval_mining(&self) {
+        if let Some(task) = self.interval_mining.lock().unwrap().take() {
+            task.abort();
+        }
+    }
+}
+
+impl<S> Drop for DevRpc<S> {
+    // The mining task holds the sequencer, so it would keep mining after the server is gone.
+    fn drop(&mut self) {
+        if let Some(task) = self.interval_mining.get_mut().unwrap().take() {
+            task.abort();
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Sequencer + Send + Sync + 'static> DevApiServer for DevRpc<S> {
+    async fn mine(&self, num_blocks: u64, interval: Option<u64>) -> Result<(), Error> {
+        let mut sequencer = self.sequencer.write().await;
+        ensure_not_frozen(&*sequencer)?;
+        sequencer.mine_blocks(num_blocks, interval)?;
+        self.audit_log.record("dev_mine", caller_ip(), None, None);
+        Ok(())
+    }
+
+    async fn replay_blocks(
+        &self,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<BlockReplayResult>, Error> {
+        Ok(self
+            .sequencer
+            .read()
+            .await
+            .replay_blocks(BlockNumber(from_block), BlockNumber(to_block))
+            .into_iter()
+            .map(|(block_number, result)| BlockReplayResult {
+                block_number: block_number.0,
+                state_matches: result.as_ref().map_or(false, |matches| *matches),
+                error: result.err().map(|err| err.to_string()),
+            })
+            .collect())
+    }
+
+    async fn deploy_contract(
+        &self,
+        contract_class: FlattenedSierraClass,
+        constructor_calldata: Vec<FieldElement>,
+        salt: FieldElement,
+    ) -> Result<DeployedContract, Error> {
+        let mut sequencer = self.sequencer.write().await;
+        ensure_not_frozen(&*sequencer)?;
+
+        let chain_id = FieldElement::from_hex_be(&sequencer.chain_id().as_hex())
+            .map_err(|_| Error::from(StarknetApiError::InternalServerError))?;
+
+        // The transactions are sent from the first predeployed account.
+        let account = sequencer
+            .predeployed_accounts()
+            .into_iter()
+            .next()
+            .ok_or(Error::from(DevApiError::NoPredeployedAccount))?;
+
+        // The `__execute__` calldata below follows the call array format of the default account
+        // contract, which other account classes may not accept.
+        if account.class_hash != ClassHash(*DEFAULT_ACCOUNT_CONTRACT_CLASS_HASH) {
+            return Err(Error::from(DevApiError::UnsupportedAccountClass));
+        }
+
+        let sender_address = FieldElement::from(*account.account_address.0.key());
+        let signing_key = SigningKey::from_secret_scalar(FieldElement::from(account.private_key));
+        let max_fee = FieldElement::from(DEPLOY_CONTRACT_MAX_FEE);
+
+        let mut nonce = FieldElement::from(
+            sequencer
+                .nonce_at(BlockId::Tag(BlockTag::Pending), account.account_address)
+                .map_err(|_| Error::from(StarknetApiError::InternalServerError))?
+                .0,
+        );
+
+        let class_hash = contract_class.class_hash();
+
+        if !sequencer.is_class_declared(ClassHash(StarkFelt::from(class_hash))) {
+            let raw_class_str = serde_json::to_string(&contract_class)?;
+            let compiled_class_hash =
+                compiled_class_hash_from_flattened_sierra_class(&raw_class_str)
+                    .map_err(|_| Error::from(StarknetApiError::InvalidContractClass))?;
+            let casm = casm_contract_class_from_flattened_sierra_class(&raw_class_str)
+                .map_err(|_| Error::from(StarknetApiError::InvalidContractClass))?;
+            let blockifier_contract_class = ContractClassV1::try_from(casm.clone())
+                .map_err(|_| Error::from(StarknetApiError::InvalidContractClass))?;
+
+            let transaction_hash = compute_declare_v2_transaction_hash(
+                sender_address,
+                class_hash,
+                max_fee,
+                chain_id,
+                nonce,
+                FieldElement::from(compiled_class_hash.0),
+            );
+
+            let transaction = DeclareTransactionV2 {
+                transaction_hash: TransactionHash(StarkFelt::from(transaction_hash)),
+                class_hash: ClassHash(StarkFelt::from(class_hash)),
+                sender_address: account.account_address,
+                nonce: Nonce(StarkFelt::from(nonce)),
+                max_fee: Fee(DEPLOY_CONTRACT_MAX_FEE.into()),
+                signature: sign_transaction(&signing_key, transaction_hash)?,
+                compiled_class_hash,
+            };
+
+            sequencer
+                .add_declare_transaction(
+                    DeclareTransaction {
+                        tx: starknet_api::transaction::DeclareTransaction::V2(transaction),
+                        contract_class: ContractClass::V1(blockifier_contract_class),
+                    },
+                    Some(contract_class.abi.clone()),
+                    Some(casm),
+                )
+                .map_err(declare_error)?;
+
+            ensure_transaction_included(&*sequencer, transaction_hash)?;
+
+            nonce += FieldElement::ONE;
+        }
+
+        // Deploy the contract through the UDC.
+        let deploy_calldata = [
+            vec![
+                class_hash,
+                salt,
+                FieldElement::ZERO, // Not unique.
+                FieldElement::from(constructor_calldata.len()),
+            ],
+            constructor_calldata.clone(),
+        ]
+        .concat();
+
+        let execute_calldata = [
+            vec![
+                FieldElement::ONE, // Call array length.
+                FieldElement::from(*sequencer.universal_deployer_address().0.key()),
+                get_selector_from_name("deployContract").expect("valid selector name"),
+                FieldElement::ZERO, // Calldata offset.
+                FieldElement::from(deploy_calldata.len()),
+                FieldElement::from(deploy_calldata.len()),
+            ],
+            deploy_calldata,
+        ]
+        .concat();
+
+        let transaction_hash = compute_invoke_v1_transaction_hash(
+            sender_address,
+            &execute_calldata,
+            max_fee,
+            chain_id,
+            nonce,
+        );
+
+        let transaction = InvokeTransactionV1 {
+            transaction_hash: TransactionHash(StarkFelt::from(transaction_hash)),
+            sender_address: account.account_address,
+            nonce: Nonce(StarkFelt::from(nonce)),
+            calldata: Calldata(Arc::new(
+                execute_calldata.into_iter().map(StarkFelt::from).collect(),
+            )),
+            max_fee: Fee(DEPLOY_CONTRACT_MAX_FEE.into()),
+            signature: sign_transaction(&signing_key, transaction_hash)?,
+        };
+
+        sequencer
+            .add_account_transaction(AccountTransaction::Invoke(InvokeTransaction::V1(
+                transaction,
+            )))
+            .map_err(|_| Error::from(StarknetApiError::InternalServerError))?;
+
+        ensure_transaction_included(&*sequencer, transaction_hash)?;
+
+        self.audit_log.record(
+            "dev_deployContract",
+            caller_ip(),
+            Some(sender_address),
+            Some(transaction_hash),
+        );
+
+        // Contracts deployed with `unique` unset have the same address as if they were deployed
+        // from the zero address.
+        let contract_address = calculate_contract_address(
+            ContractAddressSalt(StarkFelt::from(salt)),
+            ClassHash(StarkFelt::from(class_hash)),
+            &Calldata(Arc::new(
+                constructor_calldata
+                    .into_iter()
+                    .map(StarkFelt::from)
+                    .collect(),
+            )),
+            ContractAddress::default(),
+        )
+        .map_err(|_| Error::from(StarknetApiError::InternalServerError))?;
+
+        Ok(DeployedContract {
+            contract_address: FieldElement::from(*contract_address.0.key()),
+            class_hash,
+        })
+    }
+
+    async fn send_message_to_l2(
+        &self,
+        from_address: FieldElement,
+        to_address: FieldElement,
+        selector: FieldElement,
+        payload: Vec<FieldElement>,
+    ) -> Result<FieldElement, Error> {
+        let mut sequencer = self.sequencer.write().await;
+        ensure_not_frozen(&*sequencer)?;
+
+        let transaction_hash = sequencer
+            .send_message_to_l2(
+                StarkFelt::from(from_address),
+                ContractAddress(patricia_key!(to_address)),
+                EntryPointSelector(StarkFelt::from(selector)),
+                payload.into_iter().map(StarkFelt::from).collect(),
+            )
+            .map_err(|_| Error::from(StarknetApiError::InternalServerError))?;
+        let transaction_hash = FieldElement::from(transaction_hash.0);
+
+        ensure_transaction_included(&*sequencer, transaction_hash)?;
+
+        self.audit_log
+            .record("dev_sendMessageToL2", None, Some(transaction_hash));
+
+        Ok(transaction_hash)
+    }
+
+    async fn messages_to_l1(&self) -> Result<Vec<MessageToL1Entry>, Error> {
+        Ok(self
+            .sequencer
+            .read()
+            .await
+            .messages_to_l1()
+            .into_iter()
+            .map(|(block_number, message)| {
+                let mut to_address = [0u8; 32];
+                to_address[12..].copy_from_slice(message.to_address.0.as_bytes());
+
+                MessageToL1Entry {
+                    block_number: block_number.0,
+                    from_address: FieldElement::from(*message.from_address.0.key()),
+                    to_address: FieldElement::from_bytes_be(&to_address)
+                        .expect("an Ethereum address fits in a felt"),
+                    payload: message
+                        .payload
+                        .0
+                        .into_iter()
+                        .map(FieldElement::from)
+                        .collect(),
+                }
+            })
+            .collect())
+    }
+
+    async fn entrypoint_stats(&self) -> Result<Vec<EntrypointStatsEntry>, Error> {
+        Ok(self
+            .sequencer
+            .read()
+            .await
+            .entrypoint_stats()
+            .ok_or(Error::from(DevApiError::EntrypointTracingDisabled))?
+            .into_iter()
+            .map(|(class_hash, selector, stats)| EntrypointStatsEntry {
+                class_hash: class_hash.0.into(),
+                selector: selector.0.into(),
+                calls: stats.calls,
+                steps: stats.steps,
+                memory_holes: stats.memory_holes,
+            })
+            .collect())
+    }
+
+    async fn set_storage_at(
+        &self,
+        contract_address: FieldElement,
+        key: FieldElement,
+        value: FieldElement,
+    ) -> Result<(), Error> {
+        let mut sequencer = self.sequencer.write().await;
+        ensure_not_frozen(&*sequencer)?;
+        sequencer.set_storage_at(
+            ContractAddress(patricia_key!(contract_address)),
+            StorageKey(patricia_key!(key)),
+            StarkFelt::from(value),
+        )?;
+        self.audit_log
+            .record("dev_setStorageAt", caller_ip(), None, None);
+        Ok(())
+    }
+
+    async fn set_nonce(
+        &self,
+        contract_address: FieldElement,
+        nonce: FieldElement,
+    ) -> Result<(), Error> {
+        let mut sequencer = self.sequencer.write().await;
+        ensure_not_frozen(&*sequencer)?;
+        sequencer.set_nonce(
+            ContractAddress(patricia_key!(contract_address)),
+            Nonce(StarkFelt::from(nonce)),
+        )?;
+        self.audit_log
+            .record("dev_setNonce", caller_ip(), None, None);
+        Ok(())
+    }
+
+    async fn set_balance(
+        &self,
+        contract_address: FieldElement,
+        balance: FieldElement,
+    ) -> Result<(), Error> {
+        let balance = starkfelt_to_u128(StarkFelt::from(balance))
+            .map_err(|_| Error::from(StarknetApiError::InvalidCallData))?;
+
+        let mut sequencer = self.sequencer.write().await;
+        ensure_not_frozen(&*sequencer)?;
+        sequencer.set_balance(ContractAddress(patricia_key!(contract_address)), balance)?;
+        self.audit_log
+            .record("dev_setBalance", caller_ip(), None, None);
+        Ok(())
+    }
+
+    async fn set_code(
+        &self,
+        contract_address: FieldElement,
+        class_hash: FieldElement,
+    ) -> Result<(), Error> {
+        let mut sequencer = self.sequencer.write().await;
+        ensure_not_frozen(&*sequencer)?;
+        sequencer
+            .set_class_hash_at(
+                ContractAddress(patricia_key!(contract_address)),
+                ClassHash(StarkFelt::from(class_hash)),
+            )
+            .map_err(|_| Error::from(StarknetApiError::ClassHashNotFound))?;
+        self.audit_log
+            .record("dev_setCode", caller_ip(), None, None);
+        Ok(())
+    }
+
+    async fn set_automine(&self, enabled: bool) -> Result<(), Error> {
+        let mut sequencer = self.sequencer.write().await;
+        ensure_not_frozen(&*sequencer)?;
+        if enabled {
+            self.stop_interval_mining();
+        }
+        sequencer.set_auto_mining(enabled)?;
+        self.audit_log
+            .record("dev_setAutomine", caller_ip(), None, None);
+        Ok(())
+    }
+
+    async fn set_interval_mining(&self, interval: u64) -> Result<(), Error> {
+        let mut sequencer = self.sequencer.write().await;
+        ensure_not_frozen(&*sequencer)?;
+        self.stop_interval_mining();
+        if interval > 0 {
+            sequencer.set_auto_mining(false)?;
+            *self.interval_mining.lock().unwrap() = Some(spawn_interval_mining(
+                self.sequencer.clone(),
+                Duration::from_millis(interval),
+            ));
+        }
+        self.audit_log
+            .record("dev_setIntervalMining", caller_ip(), None, None);
+        Ok(())
+    }
+
+    async fn increase_time(&self, seconds: u64) -> Result<(), Error> {
+        let mut sequencer = self.sequencer.write().await;
+        ensure_not_frozen(&*sequencer)?;
+        sequencer
+            .increase_time(seconds)
+            .map_err(|_| Error::from(DevApiError::TimestampOutOfRange))?;
+        self.audit_log
+            .record("dev_increaseTime", caller_ip(), None, None);
+        Ok(())
+    }
+
+    async fn set_next_block_timestamp(&self, timestamp: u64) -> Result<(), Error> {
+        let mut sequencer = self.sequencer.write().await;
+        ensure_not_frozen(&*sequencer)?;
+        sequencer.set_next_block_timestamp(timestamp).map_err(|_| {
+            match i64::try_from(timestamp) {
+                Ok(_) => Error::from(DevApiError::TimestampBeforeLatestBlock),
+                Err(_) => Error::from(DevApiError::TimestampOutOfRange),
+            }
+        })?;
+        self.audit_log
+            .record("dev_setNextBlockTimestamp", None, None);
+        Ok(())
+    }
+
+    async fn set_log_filter(&self, filter: String) -> Result<(), Error> {
+        let log_filter = self
+            .log_filter
+            .as_ref()
+            .ok_or(Error::from(DevApiError::LogFilterReloadUnavailable))?;
+
+        log_filter.reload(&filter).map_err(|reason| {
+            Error::Call(CallError::Custom(ErrorObject::owned(
+                DevApiError::InvalidLogFilter as i32,
+                DevApiError::InvalidLogFilter.to_string(),
+                Some(reason),
+            )))
+        })?;
+
+        self.audit_log
+            .record("dev_setLogFilter", caller_ip(), None, None);
+        Ok(())
+    }
+
+    async fn snapshot(&self) -> Result<u64, Error> {
+        let snapshot_id = self
+            .sequencer
+            .write()
+            .await
+            .snapshot()
+            .map_err(|_| Error::from(DevApiError::PendingBlockNotEmpty))?;
+        self.audit_log
+            .record("dev_snapshot", caller_ip(), None, None);
+        Ok(snapshot_id)
+    }
+
+    async fn revert(&self, snapshot_id: u64) -> Result<bool, Error> {
+        let mut sequencer = self.sequencer.write().await;
+        ensure_not_frozen(&*sequencer)?;
+        let reverted = sequencer.revert(snapshot_id)?;
+        self.audit_log.record("dev_revert", caller_ip(), None, None);
+        Ok(reverted)
+    }
+}
+
+fn spawn_interval_mining<S: Sequencer + Send + Sync + 'static>(
+    sequencer: Arc<RwLock<S>>,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        // The first tick completes immediately.
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+
+            let mut sequencer = sequencer.write().await;
+            if sequencer.is_frozen() {
+                continue;
+            }
+            if let Err(err) = sequencer.generate_new_block() {
+                warn!("Failed to mine block: {err}");
+            }
+        }
+    })
+}
+
+fn sign_transaction(
+    signing_key: &SigningKey,
+    transaction_hash: FieldElement,
+) -> Result<TransactionSignature, Error> {
+    let signature = signing_key
+        .sign(&transaction_hash)
+        .map_err(|_| Error::from(StarknetApiError::InternalServerError))?;
+
+    Ok(TransactionSignature(vec![
+        StarkFelt::from(signature.r),
+        StarkFelt::from(signature.s),
+    ]))
+}
+
+fn ensure_transaction_included<S: Sequencer>(
+    sequencer: &S,
+    transaction_hash: FieldElement,
+) -> Result<(), Error> {
+    match sequencer.rejected_transaction_reason(&TransactionHash(StarkFelt::from(transaction_hash)))
+    {
+        Some(reason) => Err(Error::Call(CallError::Custom(ErrorObject::owned(
+            DevApiError::TransactionRejected as i32,
+            DevApiError::TransactionRejected.to_string(),
+            Some(reason),
+        )))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use katana_core::starknet::StarknetConfig;
+
+    use super::*;
+    use crate::test_utils::{create_dev_rpc, test_account_path, test_contract_class};
+
+    #[tokio::test]
+    async fn test_deploy_contract() {
+        let dev = create_dev_rpc(StarknetConfig::default());
+        let contract_class = test_contract_class();
+        let class_hash = contract_class.class_hash();
+
+        let deployed = dev
+            .deploy_contract(contract_class.clone(), vec![], FieldElement::from(42u8))
+            .await
+            .unwrap();
+        assert_eq!(deployed.class_hash, class_hash);
+
+        let sequencer = dev.sequencer.read().await;
+        assert!(sequencer.is_class_declared(ClassHash(StarkFelt::from(class_hash))));
+        assert_eq!(
+            sequencer
+                .class_hash_at(
+                    BlockId::Tag(BlockTag::Latest),
+                    ContractAddress(patricia_key!(deployed.contract_address)),
+                )
+                .unwrap(),
+            ClassHash(StarkFelt::from(class_hash))
+        );
+        drop(sequencer);
+
+        // Deploying again with another salt reuses the declared class.
+        let redeployed = dev
+            .deploy_contract(contract_class, vec![], FieldElement::from(43u8))
+            .await
+            .unwrap();
+        assert_ne!(redeployed.contract_address, deployed.contract_address);
+    }
+
+    #[tokio::test]
+    async fn test_deploy_contract_rejects_other_account_classes() {
+        let dev = create_dev_rpc(StarknetConfig {
+            account_path: Some(test_account_path()),
+            ..Default::default()
+        });
+
+        let err = dev
+            .deploy_contract(test_contract_class(), vec![], FieldElement::ONE)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::Call(CallError::Custom(ref error)) if error.code() == DevApiError::UnsupportedAccountClass as i32)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_set_interval_mining() {
+        let dev = create_dev_rpc(StarknetConfig::default());
+        let sequencer = dev.sequencer.clone();
+        let total_blocks = || async { sequencer.read().await.starknet.blocks.total_blocks() };
+
+        dev.set_interval_mining(10).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(
+            total_blocks().await > 0,
+            "blocks must be mined at the interval"
+        );
+
+        // A zero interval stops the mining.
+        dev.set_interval_mining(0).await.unwrap();
+        let mined = total_blocks().await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(total_blocks().await, mined);
+
+        // So does dropping the server.
+        dev.set_interval_mining(10).await.unwrap();
+        drop(dev);
+        let mined = total_blocks().await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(total_blocks().await, mined);
+    }
+}
@@ -0,0 +1,183 @@
+use serde_json::{json, Value};
+
+use crate::{dev, katana, starknet};
+
+/// The version of the OpenRPC specification the document follows.
+const OPENRPC_VERSION: &str = "1.2.6";
+
+/// The signature of a method, as described in the OpenRPC document.
+///
+/// The methods can't be introspected once registered, so each API declares the signatures of
+/// its methods in a table kept next to its `#[rpc]` trait.
+pub(crate) struct MethodSpec {
+    /// The full name of the method, including its namespace.
+    pub(crate) name: &'static str,
+    /// The name and type of each parameter, in order. Parameters of an `Option` type are optional.
+    pub(crate) params: &'static [(&'static str, &'static str)],
+    pub(crate) result: &'static str,
+}
+
+impl MethodSpec {
+    pub(crate) const fn new(
+        name: &'static str,
+        params: &'static [(&'static str, &'static str)],
+        result: &'static str,
+    ) -> Self {
+        Self {
+            name,
+            params,
+            result,
+        }
+    }
+}
+
+const METHODS: &[MethodSpec] = &[MethodSpec::new("rpc.discover", &[], "OpenrpcDocument")];
+
+fn method_spec(name: &str) -> Option<&'static MethodSpec> {
+    [
+        METHODS,
+        starknet::api::METHODS,
+        katana::api::METHODS,
+        dev::api::METHODS,
+    ]
+    .into_iter()
+    .flatten()
+    .find(|spec| spec.name == name)
+}
+
+/// Returns the JSON schema of a type from the method tables. Types which aren't primitives are
+/// only named.
+fn schema(ty: &str) -> Value {
+    match ty {
+        "()" => json!({ "type": "null" }),
+        "bool" => json!({ "type": "boolean" }),
+        "u64" | "usize" => json!({ "type": "integer", "minimum": 0 }),
+        "String" => json!({ "type": "string" }),
+        _ => match ty.strip_prefix("Vec<").and_then(|ty| ty.strip_suffix('>')) {
+            Some(item) => json!({ "type": "array", "items": schema(item) }),
+            None => json!({ "title": ty }),
+        },
+    }
+}
+
+fn content_descriptor(name: &str, ty: &str) -> Value {
+    match ty
+        .strip_prefix("Option<")
+        .and_then(|ty| ty.strip_suffix('>'))
+    {
+        Some(ty) => json!({ "name": name, "required": false, "schema": schema(ty) }),
+        None => json!({ "name": name, "required": true, "schema": schema(ty) }),
+    }
+}
+
+fn method_object(name: &str) -> Value {
+    match method_spec(name) {
+        Some(spec) => json!({
+            "name": name,
+            "params": spec
+                .params
+                .iter()
+                .map(|(name, ty)| content_descriptor(name, ty))
+                .collect::<Vec<_>>(),
+            "result": content_descriptor("result", spec.result),
+        }),
+        // Leave out what isn't known rather than describe the method wrongly.
+        None => json!({ "name": name }),
+    }
+}
+
+/// Builds the OpenRPC document served by `rpc.discover`.
+///
+/// The document lists every method exposed by the server, along with its parameters and result
+/// as declared in the method tables of the APIs.
+pub fn openrpc_document<'a>(method_names: impl Iterator<Item = &'a str>) -> Value {
+    let mut method_names = method_names.collect::<Vec<_>>();
+    method_names.sort_unstable();
+
+    let methods = method_names
+        .into_iter()
+        .map(method_object)
+        .collect::<Vec<_>>();
+
+    json!({
+        "openrpc": OPENRPC_VERSION,
+        "info": {
+            "title": "Katana",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "methods": methods,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use katana_core::starknet::StarknetConfig;
+
+    use super::*;
+    use crate::{
+        config::RpcConfig,
+        test_utils::{create_audit_log, create_sequencer},
+        KatanaNodeRpc,
+    };
+
+    #[test]
+    fn test_method_params_and_result() {
+        let document = openrpc_document(["starknet_getStorageAt", "dev_mine"].into_iter());
+
+        assert_eq!(
+            document["methods"][1],
+            json!({
+                "name": "starknet_getStorageAt",
+                "params": [
+                    {
+                        "name": "contract_address",
+                        "required": true,
+                        "schema": { "title": "FieldElement" },
+                    },
+                    { "name": "key", "required": true, "schema": { "title": "FieldElement" } },
+                    { "name": "block_id", "required": true, "schema": { "title": "BlockId" } },
+                ],
+                "result": {
+                    "name": "result",
+                    "required": true,
+                    "schema": { "title": "FieldElement" },
+                },
+            })
+        );
+        assert_eq!(
+            document["methods"][0]["params"][1],
+            json!({
+                "name": "interval",
+                "required": false,
+                "schema": { "type": "integer", "minimum": 0 },
+            })
+        );
+        assert_eq!(
+            document["methods"][0]["result"]["schema"],
+            json!({ "type": "null" })
+        );
+    }
+
+    #[test]
+    fn test_unknown_method_has_no_params() {
+        let document = openrpc_document(["katana_unknown"].into_iter());
+        assert_eq!(document["methods"][0], json!({ "name": "katana_unknown" }));
+    }
+
+    #[tokio::test]
+    async fn test_every_method_is_described() {
+        let methods = KatanaNodeRpc::new(
+            create_sequencer(StarknetConfig::default()),
+            RpcConfig::default(),
+        )
+        .methods(create_audit_log())
+        .unwrap();
+
+        for name in methods.method_names() {
+            assert!(
+                method_spec(name).is_some(),
+                "{name} is missing from the method tables"
+            );
+        }
+    }
+}
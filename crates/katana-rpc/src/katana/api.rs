@@ -3,9 +3,32 @@ use jsonrpsee::{
     proc_macros::rpc,
     types::{error::CallError, ErrorObject},
 };
+use katana_core::starknet::{event::EventOrder, policy::DeclarePolicy};
+use serde::{Deserialize, Serialize};
+use starknet::{
+    core::types::FieldElement,
+    providers::jsonrpc::models::{
+        BlockId, BroadcastedTransaction, EventFilter, EventsPage, FeeEstimate, Transaction,
+    },
+};
+
+use crate::discover::MethodSpec;
 
 #[derive(thiserror::Error, Clone, Copy, Debug)]
-pub enum KatanaApiError {}
+pub enum KatanaApiError {
+    #[error("Audit log is not configured")]
+    AuditLogNotConfigured = 100,
+    #[error("The chain is frozen")]
+    ChainFrozen = 101,
+    #[error("Class is not allowed to be declared")]
+    ClassNotDeclarable = 102,
+    #[error("Transaction execution failed")]
+    TransactionExecutionFailed = 103,
+    #[error("The overall fee overflows")]
+    FeeOverflow = 104,
+    #[error("Class hash not found")]
+    ClassHashNotFound = 105,
+}
 
 impl From<KatanaApiError> for Error {
     fn from(err: KatanaApiError) -> Self {
@@ -17,8 +40,178 @@ impl From<KatanaApiError> for Error {
     }
 }
 
+/// The fee estimates of a bundle of transactions executed one after the other.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleFeeEstimate {
+    /// The fee estimate of each transaction, in the order they were given.
+    pub estimates: Vec<FeeEstimate>,
+    /// The sum of the overall fees of the whole bundle.
+    pub overall_fee: u64,
+}
+
+/// The gas price of a block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GasPriceEntry {
+    pub block_number: u64,
+    pub gas_price: u128,
+}
+
+/// A transaction that was rejected by the sequencer, with the reason of the rejection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RejectedTransaction {
+    pub transaction_hash: FieldElement,
+    pub reason: String,
+}
+
+/// The execution cost of the calls made to the contracts of a class within a block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassExecutionStatsEntry {
+    pub class_hash: FieldElement,
+    pub calls: u64,
+    /// The Cairo steps of the calls, excluding the steps of their inner calls.
+    pub steps: u64,
+}
+
+/// A chunk of the transactions of a block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockTransactionsPage {
+    pub transactions: Vec<Transaction>,
+    /// Set if the block has more transactions.
+    pub continuation_token: Option<String>,
+}
+
 #[rpc(server, client, namespace = "katana")]
 pub trait KatanaApi {
     #[method(name = "generateBlock")]
     async fn generate_block(&self) -> Result<(), Error>;
+
+    /// Stops producing blocks and rejects every write until `katana_unfreeze` is called.
+    #[method(name = "freeze")]
+    async fn freeze(&self) -> Result<(), Error>;
+
+    #[method(name = "unfreeze")]
+    async fn unfreeze(&self) -> Result<(), Error>;
+
+    #[method(name = "getDeclarePolicy")]
+    async fn declare_policy(&self) -> Result<DeclarePolicy, Error>;
+
+    #[method(name = "setDeclarePolicy")]
+    async fn set_declare_policy(&self, policy: DeclarePolicy) -> Result<(), Error>;
+
+    #[method(name = "setAuditLogEnabled")]
+    async fn set_audit_log_enabled(&self, enabled: bool) -> Result<(), Error>;
+
+    #[method(name = "getClassAbi")]
+    async fn class_abi(&self, class_hash: FieldElement) -> Result<String, Error>;
+
+    #[method(name = "getGasPriceHistory")]
+    async fn gas_price_history(
+        &self,
+        from_block: u64,
+        to_block: Option<u64>,
+    ) -> Result<Vec<GasPriceEntry>, Error>;
+
+    #[method(name = "estimateFeeBundle")]
+    async fn estimate_fee_bundle(
+        &self,
+        transactions: Vec<BroadcastedTransaction>,
+        block_id: BlockId,
+    ) -> Result<BundleFeeEstimate, Error>;
+
+    #[method(name = "getRejectedTransaction")]
+    async fn rejected_transaction(
+        &self,
+        transaction_hash: FieldElement,
+    ) -> Result<RejectedTransaction, Error>;
+
+    #[method(name = "getClassExecutionStats")]
+    async fn class_execution_stats(
+        &self,
+        block_id: BlockId,
+    ) -> Result<Vec<ClassExecutionStatsEntry>, Error>;
+
+    /// Returns the transactions of a block in chunks of at most `chunk_size`, for blocks too large
+    /// to be fetched at once with `starknet_getBlockWithTxs`.
+    #[method(name = "getBlockTransactions")]
+    async fn block_transactions(
+        &self,
+        block_id: BlockId,
+        continuation_token: Option<String>,
+        chunk_size: u64,
+    ) -> Result<BlockTransactionsPage, Error>;
+
+    /// Same as `starknet_getEvents`, but the events can be returned newest first and the range
+    /// can be restricted to the last `last_blocks` blocks of the filter. The continuation tokens
+    /// encode the order they were created with and can't be used with a different one.
+    #[method(name = "getEvents")]
+    async fn events(
+        &self,
+        filter: EventFilter,
+        order: Option<EventOrder>,
+        last_blocks: Option<u64>,
+        continuation_token: Option<String>,
+        chunk_size: u64,
+    ) -> Result<EventsPage, Error>;
 }
+
+/// The signatures of the methods of [`KatanaApi`], for the OpenRPC document.
+pub(crate) const METHODS: &[MethodSpec] = &[
+    MethodSpec::new("katana_generateBlock", &[], "()"),
+    MethodSpec::new("katana_freeze", &[], "()"),
+    MethodSpec::new("katana_unfreeze", &[], "()"),
+    MethodSpec::new("katana_getDeclarePolicy", &[], "DeclarePolicy"),
+    MethodSpec::new(
+        "katana_setDeclarePolicy",
+        &[("policy", "DeclarePolicy")],
+        "()",
+    ),
+    MethodSpec::new("katana_setAuditLogEnabled", &[("enabled", "bool")], "()"),
+    MethodSpec::new(
+        "katana_getClassAbi",
+        &[("class_hash", "FieldElement")],
+        "String",
+    ),
+    MethodSpec::new(
+        "katana_getGasPriceHistory",
+        &[("from_block", "u64"), ("to_block", "Option<u64>")],
+        "Vec<GasPriceEntry>",
+    ),
+    MethodSpec::new(
+        "katana_estimateFeeBundle",
+        &[
+            ("transactions", "Vec<BroadcastedTransaction>"),
+            ("block_id", "BlockId"),
+        ],
+        "BundleFeeEstimate",
+    ),
+    MethodSpec::new(
+        "katana_getRejectedTransaction",
+        &[("transaction_hash", "FieldElement")],
+        "RejectedTransaction",
+    ),
+    MethodSpec::new(
+        "katana_getClassExecutionStats",
+        &[("block_id", "BlockId")],
+        "Vec<ClassExecutionStatsEntry>",
+    ),
+    MethodSpec::new(
+        "katana_getBlockTransactions",
+        &[
+            ("block_id", "BlockId"),
+            ("continuation_token", "Option<String>"),
+            ("chunk_size", "u64"),
+        ],
+        "BlockTransactionsPage",
+    ),
+    MethodSpec::new(
+        "katana_getEvents",
+        &[
+            ("filter", "EventFilter"),
+            ("order", "Option<EventOrder>"),
+            ("last_blocks", "Option<u64>"),
+            ("continuation_token", "Option<String>"),
+            ("chunk_size", "u64"),
+        ],
+        "EventsPage",
+    ),
+];
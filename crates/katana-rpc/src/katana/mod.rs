@@ -1,27 +1,491 @@
 use std::sync::Arc;
 
-use jsonrpsee::core::{async_trait, Error};
-use katana_core::sequencer::Sequencer;
+use jsonrpsee::{
+    core::{async_trait, Error},
+    types::{error::CallError, ErrorObject},
+};
+use katana_core::{
+    sequencer::{DeclareError, EstimateBundleError, Sequencer},
+    starknet::{
+        event::{EventCursor, EventOrder},
+        policy::DeclarePolicy,
+    },
+};
+use serde_json::json;
+use starknet::{
+    core::types::FieldElement,
+    providers::jsonrpc::models::{
+        BlockId, BlockTag, BroadcastedTransaction, EventFilter, EventsPage, FeeEstimate,
+    },
+};
+use starknet_api::{
+    block::BlockNumber, core::ClassHash, hash::StarkFelt, transaction::TransactionHash,
+};
 use tokio::sync::RwLock;
 
-use self::api::KatanaApiServer;
+use self::api::{
+    BlockTransactionsPage, BundleFeeEstimate, ClassExecutionStatsEntry, GasPriceEntry,
+    KatanaApiError, KatanaApiServer, RejectedTransaction,
+};
+use crate::{
+    audit::{caller_ip, AuditLog},
+    starknet::{
+        api::StarknetApiError, broadcasted_to_account_transaction, emitted_event_to_rpc_event,
+    },
+    utils::transaction::convert_inner_to_rpc_tx,
+};
 
 pub mod api;
 
+/// The maximum number of transactions returned by `katana_getBlockTransactions` at once.
+const MAX_BLOCK_TRANSACTIONS_CHUNK_SIZE: u64 = 1000;
+
 pub struct KatanaRpc<S> {
     sequencer: Arc<RwLock<S>>,
+    audit_log: Arc<AuditLog>,
 }
 
 impl<S: Sequencer + Send + Sync + 'static> KatanaRpc<S> {
-    pub fn new(sequencer: Arc<RwLock<S>>) -> Self {
-        Self { sequencer }
+    pub fn new(sequencer: Arc<RwLock<S>>, audit_log: Arc<AuditLog>) -> Self {
+        Self {
+            sequencer,
+            audit_log,
+        }
     }
 }
 
 #[async_trait]
 impl<S: Sequencer + Send + Sync + 'static> KatanaApiServer for KatanaRpc<S> {
     async fn generate_block(&self) -> Result<(), Error> {
-        self.sequencer.write().await.generate_new_block()?;
+        let mut sequencer = self.sequencer.write().await;
+        ensure_not_frozen(&*sequencer)?;
+        sequencer.generate_new_block()?;
+        self.audit_log
+            .record("katana_generateBlock", caller_ip(), None, None);
+        Ok(())
+    }
+
+    async fn freeze(&self) -> Result<(), Error> {
+        self.sequencer.write().await.set_frozen(true);
+        self.audit_log
+            .record("katana_freeze", caller_ip(), None, None);
+        Ok(())
+    }
+
+    async fn unfreeze(&self) -> Result<(), Error> {
+        self.sequencer.write().await.set_frozen(false);
+        self.audit_log
+            .record("katana_unfreeze", caller_ip(), None, None);
         Ok(())
     }
+
+    async fn declare_policy(&self) -> Result<DeclarePolicy, Error> {
+        Ok(self.sequencer.read().await.declare_policy())
+    }
+
+    async fn set_declare_policy(&self, policy: DeclarePolicy) -> Result<(), Error> {
+        self.sequencer.write().await.set_declare_policy(policy);
+        self.audit_log
+            .record("katana_setDeclarePolicy", caller_ip(), None, None);
+        Ok(())
+    }
+
+    async fn set_audit_log_enabled(&self, enabled: bool) -> Result<(), Error> {
+        if !self.audit_log.is_configured() {
+            return Err(Error::from(KatanaApiError::AuditLogNotConfigured));
+        }
+
+        self.audit_log.set_enabled(enabled);
+        Ok(())
+    }
+
+    async fn class_abi(&self, class_hash: FieldElement) -> Result<String, Error> {
+        self.sequencer
+            .read()
+            .await
+            .class_abi(ClassHash(StarkFelt::from(class_hash)))
+            .ok_or(Error::from(KatanaApiError::ClassHashNotFound))
+    }
+
+    async fn gas_price_history(
+        &self,
+        from_block: u64,
+        to_block: Option<u64>,
+    ) -> Result<Vec<GasPriceEntry>, Error> {
+        Ok(self
+            .sequencer
+            .read()
+            .await
+            .gas_price_history(BlockNumber(from_block), to_block.map(BlockNumber))
+            .into_iter()
+            .map(|(block_number, gas_price)| GasPriceEntry {
+                block_number: block_number.0,
+                gas_price: gas_price.0,
+            })
+            .collect())
+    }
+
+    async fn estimate_fee_bundle(
+        &self,
+        transactions: Vec<BroadcastedTransaction>,
+        block_id: BlockId,
+    ) -> Result<BundleFeeEstimate, Error> {
+        let chain_id = FieldElement::from_hex_be(&self.sequencer.read().await.chain_id().as_hex())
+            .map_err(|_| Error::from(StarknetApiError::InternalServerError))?;
+
+        let transactions = transactions
+            .into_iter()
+            .map(|transaction| broadcasted_to_account_transaction(transaction, chain_id))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let estimates = self
+            .sequencer
+            .read()
+            .await
+            .estimate_fee_bundle(transactions, block_id)
+            .map_err(|err| match err {
+                EstimateBundleError::BlockNotFound(_) => {
+                    Error::from(StarknetApiError::BlockNotFound)
+                }
+                EstimateBundleError::TransactionFailed { index, reason } => {
+                    let err = KatanaApiError::TransactionExecutionFailed;
+                    Error::Call(CallError::Custom(ErrorObject::owned(
+                        err as i32,
+                        err.to_string(),
+                        Some(json!({ "transaction_index": index, "reason": reason })),
+                    )))
+                }
+                EstimateBundleError::Other(_) => Error::from(StarknetApiError::InternalServerError),
+            })?
+            .into_iter()
+            .map(|estimate| FeeEstimate {
+                gas_price: estimate.gas_price,
+                gas_consumed: estimate.gas_usage,
+                overall_fee: estimate.overall_fee,
+            })
+            .collect::<Vec<_>>();
+
+        let overall_fee = estimates
+            .iter()
+            .try_fold(0u64, |total, estimate| {
+                total.checked_add(estimate.overall_fee)
+            })
+            .ok_or(Error::from(KatanaApiError::FeeOverflow))?;
+
+        Ok(BundleFeeEstimate {
+            overall_fee,
+            estimates,
+        })
+    }
+
+    async fn rejected_transaction(
+        &self,
+        transaction_hash: FieldElement,
+    ) -> Result<RejectedTransaction, Error> {
+        let reason = self
+            .sequencer
+            .read()
+            .await
+            .rejected_transaction_reason(&TransactionHash(StarkFelt::from(transaction_hash)))
+            .ok_or(Error::from(StarknetApiError::TxnHashNotFound))?;
+
+        Ok(RejectedTransaction {
+            transaction_hash,
+            reason,
+        })
+    }
+
+    async fn class_execution_stats(
+        &self,
+        block_id: BlockId,
+    ) -> Result<Vec<ClassExecutionStatsEntry>, Error> {
+        Ok(self
+            .sequencer
+            .read()
+            .await
+            .class_execution_stats(block_id)
+            .ok_or(Error::from(StarknetApiError::BlockNotFound))?
+            .into_iter()
+            .map(|(class_hash, stats)| ClassExecutionStatsEntry {
+                class_hash: class_hash.0.into(),
+                calls: stats.calls,
+                steps: stats.steps,
+            })
+            .collect())
+    }
+
+    async fn block_transactions(
+        &self,
+        block_id: BlockId,
+        continuation_token: Option<String>,
+        chunk_size: u64,
+    ) -> Result<BlockTransactionsPage, Error> {
+        if chunk_size > MAX_BLOCK_TRANSACTIONS_CHUNK_SIZE {
+            return Err(Error::from(StarknetApiError::PageSizeTooBig));
+        }
+
+        // The continuation token is the index of the first transaction of the next chunk.
+        let offset = continuation_token
+            .map(|token| token.parse::<usize>())
+            .transpose()
+            .map_err(|_| Error::from(StarknetApiError::InvalidContinuationToken))?
+            .unwrap_or_default();
+
+        let block = self
+            .sequencer
+            .read()
+            .await
+            .block(block_id)
+            .ok_or(Error::from(StarknetApiError::BlockNotFound))?;
+        let block_transactions = block.transactions();
+
+        if offset > block_transactions.len() {
+            return Err(Error::from(StarknetApiError::InvalidContinuationToken));
+        }
+
+        let end = std::cmp::min(
+            offset + std::cmp::max(chunk_size, 1) as usize,
+            block_transactions.len(),
+        );
+
+        let transactions = block_transactions[offset..end]
+            .iter()
+            .map(|tx| convert_inner_to_rpc_tx(tx.clone()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| Error::from(StarknetApiError::InternalServerError))?;
+
+        Ok(BlockTransactionsPage {
+            transactions,
+            continuation_token: (end < block_transactions.len()).then(|| end.to_string()),
+        })
+    }
+
+    async fn events(
+        &self,
+        filter: EventFilter,
+        order: Option<EventOrder>,
+        last_blocks: Option<u64>,
+        continuation_token: Option<String>,
+        chunk_size: u64,
+    ) -> Result<EventsPage, Error> {
+        let sequencer = self.sequencer.read().await;
+
+        // The events of the pending block aren't indexed yet, so it is the same as the latest.
+        let block_number = |block_id: BlockId| {
+            let block_id = match block_id {
+                BlockId::Tag(BlockTag::Pending) => BlockId::Tag(BlockTag::Latest),
+                block_id => block_id,
+            };
+            sequencer.block(block_id).map(|block| block.block_number())
+        };
+
+        let from_block = block_number(filter.from_block.unwrap_or(BlockId::Number(0)))
+            .ok_or(Error::from(StarknetApiError::BlockNotFound))?;
+        let to_block = block_number(filter.to_block.unwrap_or(BlockId::Tag(BlockTag::Latest)))
+            .ok_or(Error::from(StarknetApiError::BlockNotFound))?;
+
+        let from_block = match last_blocks {
+            Some(last_blocks) => std::cmp::max(
+                from_block,
+                BlockNumber((to_block.0 + 1).saturating_sub(last_blocks)),
+            ),
+            None => from_block,
+        };
+
+        if from_block > to_block {
+            return Ok(EventsPage {
+                events: vec![],
+                continuation_token: None,
+            });
+        }
+
+        let order = order.unwrap_or_default();
+
+        let cursor = continuation_token
+            .map(|token| token.parse::<EventCursor>())
+            .transpose()
+            .map_err(|_| Error::from(StarknetApiError::InvalidContinuationToken))?;
+        if cursor.map_or(false, |cursor| {
+            !cursor.is_valid_for(order, from_block, to_block)
+        }) {
+            return Err(Error::from(StarknetApiError::InvalidContinuationToken));
+        }
+
+        let (events, cursor) = sequencer
+            .events_page(
+                from_block,
+                to_block,
+                filter.address.map(StarkFelt::from),
+                filter.keys.map(|keys| {
+                    keys.iter()
+                        .map(|key| key.iter().map(|key| (*key).into()).collect())
+                        .collect()
+                }),
+                order,
+                cursor,
+                std::cmp::max(chunk_size, 1),
+            )
+            .map_err(|_| Error::from(StarknetApiError::InternalServerError))?;
+
+        Ok(EventsPage {
+            events: events.iter().map(emitted_event_to_rpc_event).collect(),
+            continuation_token: cursor.map(|cursor| cursor.to_string()),
+        })
+    }
+}
+
+/// Rejects the write methods while the chain is frozen.
+pub(crate) fn ensure_not_frozen<S: Sequencer>(sequencer: &S) -> Result<(), Error> {
+    if sequencer.is_frozen() {
+        return Err(Error::from(KatanaApiError::ChainFrozen));
+    }
+    Ok(())
+}
+
+pub(crate) fn declare_error(err: DeclareError) -> Error {
+    match err {
+        DeclareError::ChainFrozen => Error::from(KatanaApiError::ChainFrozen),
+        DeclareError::ClassNotDeclarable(_) => Error::from(KatanaApiError::ClassNotDeclarable),
+        DeclareError::Other(_) => Error::from(StarknetApiError::InternalServerError),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blockifier::{
+        abi::abi_utils::selector_from_name, transaction::account_transaction::AccountTransaction,
+    };
+    use katana_core::{
+        constants::FEE_TOKEN_ADDRESS,
+        starknet::{policy::DeclarePolicy, StarknetConfig},
+    };
+    use starknet_api::{
+        core::Nonce,
+        transaction::{Calldata, InvokeTransaction, InvokeTransactionV1},
+    };
+
+    use super::*;
+    use crate::{
+        dev::api::DevApiServer,
+        test_utils::{create_katana_rpcs, error_code, test_account_path, test_contract_class},
+    };
+
+    #[tokio::test]
+    async fn test_class_abi() {
+        let (katana, dev) = create_katana_rpcs(StarknetConfig::default());
+        let contract_class = test_contract_class();
+        let class_hash = contract_class.class_hash();
+
+        let err = katana.class_abi(class_hash).await.unwrap_err();
+        assert_eq!(
+            error_code(err),
+            Some(KatanaApiError::ClassHashNotFound as i32)
+        );
+
+        dev.deploy_contract(contract_class.clone(), vec![], FieldElement::ONE)
+            .await
+            .unwrap();
+        assert_eq!(
+            katana.class_abi(class_hash).await.unwrap(),
+            contract_class.abi
+        );
+    }
+
+    #[tokio::test]
+    async fn test_declare_errors() {
+        let (katana, dev) = create_katana_rpcs(StarknetConfig {
+            declare_policy: DeclarePolicy::DenyAll,
+            ..Default::default()
+        });
+
+        let err = dev
+            .deploy_contract(test_contract_class(), vec![], FieldElement::ONE)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error_code(err),
+            Some(KatanaApiError::ClassNotDeclarable as i32)
+        );
+
+        let err = katana
+            .class_abi(test_contract_class().class_hash())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error_code(err),
+            Some(KatanaApiError::ClassHashNotFound as i32)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_block_transactions() {
+        let (katana, _) = create_katana_rpcs(StarknetConfig {
+            total_accounts: 2,
+            allow_zero_max_fee: true,
+            blocks_on_demand: true,
+            account_path: Some(test_account_path()),
+            ..Default::default()
+        });
+
+        // A block with three transfers.
+        {
+            let mut sequencer = katana.sequencer.write().await;
+            let accounts = sequencer.predeployed_accounts();
+            for nonce in 0..3u64 {
+                sequencer
+                    .add_account_transaction(AccountTransaction::Invoke(InvokeTransaction::V1(
+                        InvokeTransactionV1 {
+                            transaction_hash: TransactionHash(StarkFelt::from(0x100 + nonce)),
+                            sender_address: accounts[0].account_address,
+                            nonce: Nonce(StarkFelt::from(nonce)),
+                            calldata: Calldata(Arc::new(vec![
+                                *FEE_TOKEN_ADDRESS,
+                                selector_from_name("transfer").0,
+                                StarkFelt::from(3u64),
+                                *accounts[1].account_address.0.key(),
+                                StarkFelt::from(1u64),
+                                StarkFelt::from(0u64),
+                            ])),
+                            ..Default::default()
+                        },
+                    )))
+                    .unwrap();
+            }
+            sequencer.generate_new_block().unwrap();
+        }
+
+        let katana = &katana;
+        let page = move |token: Option<&str>, chunk_size: u64| {
+            katana.block_transactions(BlockId::Number(0), token.map(str::to_string), chunk_size)
+        };
+
+        let first = page(None, 2).await.unwrap();
+        assert_eq!(first.transactions.len(), 2);
+        assert_eq!(first.continuation_token.as_deref(), Some("2"));
+
+        let last = page(first.continuation_token.as_deref(), 2).await.unwrap();
+        assert_eq!(last.transactions.len(), 1);
+        assert_eq!(last.continuation_token, None);
+
+        // A token at the end of the block gives an empty page, and past it is invalid.
+        let empty = page(Some("3"), 2).await.unwrap();
+        assert!(empty.transactions.is_empty());
+        assert_eq!(empty.continuation_token, None);
+
+        for token in ["4", "not a token"] {
+            assert_eq!(
+                error_code(page(Some(token), 2).await.unwrap_err()),
+                Some(StarknetApiError::InvalidContinuationToken as i32)
+            );
+        }
+
+        assert_eq!(
+            error_code(
+                page(None, MAX_BLOCK_TRANSACTIONS_CHUNK_SIZE + 1)
+                    .await
+                    .unwrap_err()
+            ),
+            Some(StarknetApiError::PageSizeTooBig as i32)
+        );
+    }
 }
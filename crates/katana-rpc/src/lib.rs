@@ -1,16 +1,28 @@
+use audit::{AuditLog, CallerLayer};
 use config::RpcConfig;
+use dev::{api::DevApiServer, DevRpc};
 use jsonrpsee::{
     core::Error,
     server::{ServerBuilder, ServerHandle},
+    Methods,
 };
 use katana::{api::KatanaApiServer, KatanaRpc};
 use katana_core::sequencer::Sequencer;
-use std::{net::SocketAddr, sync::Arc};
+use record::{recorded_write_calls, RecordLayer, Recorder};
+use std::{net::SocketAddr, path::Path, sync::Arc};
 use tokio::sync::RwLock;
+use tower::ServiceBuilder;
 
+mod audit;
 pub mod config;
+mod dev;
+mod discover;
 mod katana;
+mod record;
+mod sse;
 mod starknet;
+#[cfg(test)]
+pub(crate) mod test_utils;
 mod utils;
 
 use self::starknet::{
@@ -18,6 +30,11 @@ use self::starknet::{
     StarknetRpc,
 };
 
+const RPC_DISCOVER_METHOD: &str = "rpc.discover";
+
+/// The version of the Starknet JSON-RPC specification implemented by the `starknet` namespace.
+pub const RPC_SPEC_VERSION: &str = "0.3.0";
+
 #[derive(Debug, Clone)]
 pub struct KatanaNodeRpc<S> {
     pub config: RpcConfig,
@@ -32,43 +49,123 @@ where
         Self { config, sequencer }
     }
 
-    pub async fn run(self) -> Result<(SocketAddr, ServerHandle), Error> {
-        let mut methods = KatanaRpc::new(self.sequencer.clone()).into_rpc();
-        methods.merge(StarknetRpc::new(self.sequencer.clone()).into_rpc())?;
-
-        let server = ServerBuilder::new()
-            .set_logger(KatanaNodeRpcLogger)
-            .build(format!("127.0.0.1:{}", self.config.port))
-            .await
-            .map_err(|_| Error::from(StarknetApiError::InternalServerError))?;
+    /// Starts one server per configured host, all serving the same methods.
+    pub async fn run(self) -> Result<Vec<(SocketAddr, ServerHandle)>, Error> {
+        self.config
+            .validate()
+            .map_err(|err| Error::Custom(err.to_string()))?;
+
+        let audit_log = Arc::new(AuditLog::new(self.config.audit_log.as_deref())?);
+        let methods = self.methods(audit_log)?;
+
+        let recorder = match &self.config.record_dir {
+            Some(dir) => Some(Arc::new(Recorder::new(dir)?)),
+            None => None,
+        };
+
+        let mut servers = Vec::with_capacity(self.config.hosts.len());
+
+        for host in &self.config.hosts {
+            let server = ServerBuilder::new()
+                .set_logger(KatanaNodeRpcLogger {
+                    slow_call_threshold: self.config.slow_call_threshold,
+                })
+                .set_middleware(
+                    ServiceBuilder::new()
+                        .layer(CallerLayer)
+                        .layer(RecordLayer::new(recorder.clone())),
+                )
+                .ping_interval(self.config.keep_alive_interval)
+                .build(SocketAddr::new(*host, self.config.port))
+                .await
+                .map_err(|_| Error::from(StarknetApiError::InternalServerError))?;
+
+            let addr = server.local_addr()?;
+            let handle = server.start(methods.clone())?;
+
+            servers.push((addr, handle));
+
+            if let Some(port) = self.config.sse_port {
+                let addr = sse::spawn(
+                    self.sequencer.clone(),
+                    SocketAddr::new(*host, port),
+                    self.config.keep_alive_interval,
+                )
+                .map_err(|err| Error::Transport(err.into()))?;
+                info!("SSE server started: http://{addr}");
+            }
+        }
+
+        Ok(servers)
+    }
 
-        let addr = server.local_addr()?;
-        let handle = server.start(methods)?;
+    /// Re-executes the calls modifying the chain from a recording made with `record_dir` set, in
+    /// the order they were served. Returns the number of calls replayed.
+    ///
+    /// The transactions are replayed as they were signed, so the node should be started with the
+    /// same options as the recorded one.
+    pub async fn replay(&self, path: &Path) -> Result<usize, Error> {
+        // The replayed calls aren't audited, as they aren't made by a client.
+        let methods = self.methods(Arc::new(AuditLog::new(None)?))?;
+        let calls = recorded_write_calls(path)?;
+
+        for call in &calls {
+            let (response, _) = methods.raw_json_request(&call.to_string()).await?;
+            if !response.success {
+                warn!("replayed call failed: {}", response.result);
+            }
+        }
+
+        Ok(calls.len())
+    }
 
-        Ok((addr, handle))
+    fn methods(&self, audit_log: Arc<AuditLog>) -> Result<Methods, Error> {
+        let mut methods = KatanaRpc::new(self.sequencer.clone(), audit_log.clone()).into_rpc();
+        methods.merge(StarknetRpc::new(self.sequencer.clone(), audit_log.clone()).into_rpc())?;
+        methods.merge(
+            DevRpc::new(
+                self.sequencer.clone(),
+                audit_log,
+                self.config.log_filter.clone(),
+            )
+            .into_rpc(),
+        )?;
+
+        let document = discover::openrpc_document(
+            methods
+                .method_names()
+                .chain(std::iter::once(RPC_DISCOVER_METHOD)),
+        );
+        methods.register_method(RPC_DISCOVER_METHOD, move |_, _| Ok(document.clone()))?;
+
+        Ok(methods.into())
     }
 }
 
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use jsonrpsee::{
     server::logger::{Logger, MethodKind, TransportProtocol},
-    tracing::info,
+    tracing::{info, warn},
     types::Params,
 };
 
 #[derive(Debug, Clone)]
-pub struct KatanaNodeRpcLogger;
+pub struct KatanaNodeRpcLogger {
+    /// Calls taking longer than this are logged as slow.
+    pub slow_call_threshold: Option<Duration>,
+}
 
 impl Logger for KatanaNodeRpcLogger {
     type Instant = std::time::Instant;
 
     fn on_connect(
         &self,
-        _remote_addr: std::net::SocketAddr,
+        remote_addr: std::net::SocketAddr,
         _request: &jsonrpsee::server::logger::HttpRequest,
         _t: TransportProtocol,
     ) {
+        audit::set_caller(remote_addr);
     }
 
     fn on_request(&self, _transport: TransportProtocol) -> Self::Instant {
@@ -87,11 +184,25 @@ impl Logger for KatanaNodeRpcLogger {
 
     fn on_result(
         &self,
-        _method_name: &str,
-        _success: bool,
-        _started_at: Self::Instant,
+        method_name: &str,
+        success: bool,
+        started_at: Self::Instant,
         _transport: TransportProtocol,
     ) {
+        let Some(threshold) = self.slow_call_threshold else {
+            return;
+        };
+
+        let elapsed = started_at.elapsed();
+        if elapsed > threshold {
+            warn!(
+                target: "rpc::slow",
+                "method: '{}' took {}ms (success: {})",
+                method_name,
+                elapsed.as_millis(),
+                success
+            );
+        }
     }
 
     fn on_response(
@@ -0,0 +1,319 @@
+//! Recording of the JSON-RPC calls served over HTTP, so that the calls modifying the chain can be
+//! replayed against a fresh node to reproduce its state.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    future::Future,
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use hyper::{body::HttpBody, header, Body, Request, Response};
+use jsonrpsee::tracing::warn;
+use katana_core::util::get_current_timestamp;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::oneshot;
+use tower::{Layer, Service};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// The methods modifying the chain, which are re-executed when replaying a recording.
+///
+/// `dev_setIntervalMining` is left out, as the mining task it starts would keep mining blocks on
+/// the wall clock during the replay, and couldn't be stopped through the served methods afterwards.
+const WRITE_METHODS: &[&str] = &[
+    "starknet_addDeclareTransaction",
+    "starknet_addDeployAccountTransaction",
+    "starknet_addInvokeTransaction",
+    "dev_mine",
+    "dev_deployContract",
+    "dev_sendMessageToL2",
+    "dev_setStorageAt",
+    "dev_setNonce",
+    "dev_setBalance",
+    "dev_setCode",
+    "dev_setAutomine",
+    "dev_increaseTime",
+    "dev_setNextBlockTimestamp",
+    "dev_snapshot",
+    "dev_revert",
+    "katana_generateBlock",
+    "katana_freeze",
+    "katana_unfreeze",
+    "katana_setDeclarePolicy",
+];
+
+#[derive(Serialize, Deserialize)]
+struct RecordEntry {
+    /// When the response was sent, in milliseconds since the UNIX epoch.
+    timestamp: u128,
+    request: Value,
+    response: Value,
+}
+
+/// A JSON lines file recording every request served over HTTP along with its response.
+#[derive(Debug)]
+pub struct Recorder {
+    file: Mutex<File>,
+}
+
+impl Recorder {
+    /// Creates a recording in `dir`, named after the current time.
+    pub fn new(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+
+        let path = dir.join(format!("{}.jsonl", get_current_timestamp().as_millis()));
+        let file = OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(path)?;
+
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    fn record(&self, request: &[u8], response: &[u8]) {
+        let entry = RecordEntry {
+            timestamp: get_current_timestamp().as_millis(),
+            request: json_or_string(request),
+            response: json_or_string(response),
+        };
+
+        let mut line = serde_json::to_string(&entry).expect("record entry should serialize");
+        line.push('\n');
+
+        if let Err(err) = self.file.lock().unwrap().write_all(line.as_bytes()) {
+            warn!("failed to write RPC record entry: {err}");
+        }
+    }
+}
+
+/// Returns the recorded calls to the methods modifying the chain, in the order they were served.
+/// Batches are split into their individual calls.
+pub fn recorded_write_calls(path: &Path) -> io::Result<Vec<Value>> {
+    let mut calls = Vec::new();
+
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let entry: RecordEntry = serde_json::from_str(&line)?;
+        let requests = match entry.request {
+            Value::Array(requests) => requests,
+            request => vec![request],
+        };
+
+        calls.extend(requests.into_iter().filter(|request| {
+            request["method"]
+                .as_str()
+                .map_or(false, |method| WRITE_METHODS.contains(&method))
+        }));
+    }
+
+    Ok(calls)
+}
+
+fn json_or_string(bytes: &[u8]) -> Value {
+    serde_json::from_slice(bytes)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(bytes).into_owned()))
+}
+
+/// Records the calls to the inner service when a [`Recorder`] is given.
+#[derive(Debug, Clone)]
+pub(crate) struct RecordLayer {
+    recorder: Option<Arc<Recorder>>,
+}
+
+impl RecordLayer {
+    pub(crate) fn new(recorder: Option<Arc<Recorder>>) -> Self {
+        Self { recorder }
+    }
+}
+
+impl<S> Layer<S> for RecordLayer {
+    type Service = RecordService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RecordService {
+            inner,
+            recorder: self.recorder.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct RecordService<S> {
+    inner: S,
+    recorder: Option<Arc<Recorder>>,
+}
+
+impl<S> Service<Request<Body>> for RecordService<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+    S::Error: Into<BoxError> + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response<Body>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        // The messages sent over WebSocket connections don't go through here, so the upgrade
+        // requests are passed through as well.
+        let recorder = match &self.recorder {
+            Some(recorder) if !request.headers().contains_key(header::UPGRADE) => recorder.clone(),
+            _ => {
+                let response = self.inner.call(request);
+                return Box::pin(async move { response.await.map_err(Into::<BoxError>::into) });
+            }
+        };
+
+        // The request body is copied while it is forwarded to the inner service, since the latter
+        // has to be called before the body can be read.
+        let (parts, mut body) = request.into_parts();
+        let (mut sender, forwarded_body) = Body::channel();
+        let (request_sender, request_receiver) = oneshot::channel();
+
+        tokio::spawn(async move {
+            let mut request = Vec::new();
+            while let Some(chunk) = body.data().await {
+                let Ok(chunk) = chunk else {
+                    sender.abort();
+                    return;
+                };
+                request.extend_from_slice(&chunk);
+                if sender.send_data(chunk).await.is_err() {
+                    return;
+                }
+            }
+            let _ = request_sender.send(request);
+        });
+
+        let response = self.inner.call(Request::from_parts(parts, forwarded_body));
+
+        Box::pin(async move {
+            let (parts, body) = response.await.map_err(Into::<BoxError>::into)?.into_parts();
+            let response = hyper::body::to_bytes(body).await?;
+
+            if let Ok(request) = request_receiver.await {
+                recorder.record(&request, &response);
+            }
+
+            Ok::<_, BoxError>(Response::from_parts(parts, Body::from(response)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use serde_json::json;
+
+    use super::*;
+
+    // A directory unique to the test and the process, so that concurrent runs don't collide.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "katana_record_{name}_{}_{}",
+            std::process::id(),
+            get_current_timestamp().as_nanos()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Responds with the body of the request.
+    struct Echo;
+
+    impl Service<Request<Body>> for Echo {
+        type Response = Response<Body>;
+        type Error = BoxError;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: Request<Body>) -> Self::Future {
+            Box::pin(async move {
+                let body = hyper::body::to_bytes(request.into_body()).await?;
+                Ok(Response::new(Body::from(body)))
+            })
+        }
+    }
+
+    #[test]
+    fn test_recorded_write_calls() {
+        let dir = test_dir("write_calls");
+        let path = dir.join("recording.jsonl");
+
+        let mint = json!({ "jsonrpc": "2.0", "id": 1, "method": "dev_setBalance", "params": [] });
+        let read = json!({ "jsonrpc": "2.0", "id": 2, "method": "starknet_chainId", "params": [] });
+        let mine = json!({ "jsonrpc": "2.0", "id": 3, "method": "dev_mine", "params": [1] });
+        let interval =
+            json!({ "jsonrpc": "2.0", "id": 4, "method": "dev_setIntervalMining", "params": [1] });
+
+        let entries = [
+            mint.clone(),
+            read.clone(),
+            json!([read, mine.clone(), interval]),
+            // Bodies which aren't JSON are recorded as strings.
+            json!("not json"),
+        ];
+        let lines = entries
+            .into_iter()
+            .map(|request| {
+                serde_json::to_string(&RecordEntry {
+                    timestamp: 0,
+                    request,
+                    response: Value::Null,
+                })
+                .unwrap()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        fs::write(&path, lines).unwrap();
+
+        assert_eq!(recorded_write_calls(&path).unwrap(), vec![mint, mine]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_record_service_round_trip() {
+        let dir = test_dir("round_trip");
+        let recorder = Arc::new(Recorder::new(&dir).unwrap());
+        let mut service = RecordLayer::new(Some(recorder)).layer(Echo);
+
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": "dev_mine", "params": [1] });
+        let response = service
+            .call(Request::new(Body::from(request.to_string())))
+            .await
+            .unwrap();
+
+        // The response is forwarded untouched.
+        let response = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(serde_json::from_slice::<Value>(&response).unwrap(), request);
+
+        let path = fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+        let entry: RecordEntry =
+            serde_json::from_str(fs::read_to_string(&path).unwrap().trim()).unwrap();
+        assert_eq!(entry.request, request);
+        assert_eq!(entry.response, request);
+        assert_eq!(recorded_write_calls(&path).unwrap(), vec![request]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,244 @@
+//! A server-sent events endpoint streaming the new block headers and events, for clients which
+//! can't open WebSocket connections to use the `starknet_subscribe*` methods.
+//!
+//! - `GET /heads` streams the headers of the sealed blocks as `newHead` events.
+//! - `GET /events` streams the events emitted in the sealed blocks as `event` events. They can be
+//!   filtered with a `from_address` parameter and one `keys` parameter per key position, listing
+//!   the accepted keys separated by commas, matched as in `starknet_getEvents`. The parameters
+//!   are URL-encoded, so the commas may be sent as `%2C`.
+
+use std::{convert::Infallible, net::SocketAddr, sync::Arc, time::Duration};
+
+use hyper::{
+    body::Bytes,
+    header,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use jsonrpsee::tracing::warn;
+use katana_core::{sequencer::Sequencer, starknet::notification::ChainNotification};
+use serde::Serialize;
+use starknet::core::types::FieldElement;
+use starknet_api::hash::StarkFelt;
+use tokio::sync::{broadcast::error::RecvError, RwLock};
+
+use crate::starknet::{block_events, block_header};
+
+/// Starts the server in the background and returns the address it listens on. A comment is sent
+/// on the streams every `keep_alive_interval`, so that proxies don't close the idle ones.
+pub(crate) fn spawn<S: Sequencer + Send + Sync + 'static>(
+    sequencer: Arc<RwLock<S>>,
+    addr: SocketAddr,
+    keep_alive_interval: Duration,
+) -> Result<SocketAddr, hyper::Error> {
+    let make_service = make_service_fn(move |_| {
+        let sequencer = sequencer.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                handle_request(sequencer.clone(), request, keep_alive_interval)
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&addr)?.serve(make_service);
+    let addr = server.local_addr();
+
+    tokio::spawn(async move {
+        if let Err(err) = server.await {
+            warn!("SSE server stopped: {err}");
+        }
+    });
+
+    Ok(addr)
+}
+
+async fn handle_request<S: Sequencer + Send + Sync + 'static>(
+    sequencer: Arc<RwLock<S>>,
+    request: Request<Body>,
+    keep_alive_interval: Duration,
+) -> Result<Response<Body>, Infallible> {
+    if request.method() != Method::GET {
+        return Ok(empty_response(StatusCode::METHOD_NOT_ALLOWED));
+    }
+
+    let body = match request.uri().path() {
+        "/heads" => stream(sequencer, keep_alive_interval, |_, notification| {
+            let ChainNotification::NewBlock(block) = notification else {
+                return vec![];
+            };
+            vec![("newHead", block_header(&block))]
+        }),
+
+        "/events" => {
+            let Some((address, keys)) = parse_event_filter(request.uri().query()) else {
+                return Ok(empty_response(StatusCode::BAD_REQUEST));
+            };
+
+            stream(
+                sequencer,
+                keep_alive_interval,
+                move |sequencer, notification| {
+                    let ChainNotification::NewBlock(block) = notification else {
+                        return vec![];
+                    };
+                    block_events(sequencer, &block, address, keys.clone())
+                        .into_iter()
+                        .map(|event| ("event", event))
+                        .collect()
+                },
+            )
+        }
+
+        _ => return Ok(empty_response(StatusCode::NOT_FOUND)),
+    };
+
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, "text/event-stream")
+        .header(header::CACHE_CONTROL, "no-cache")
+        .body(body)
+        .expect("valid response"))
+}
+
+// Streams the events produced by `notify` from each chain notification, until the client goes
+// away.
+fn stream<S, T, F>(sequencer: Arc<RwLock<S>>, keep_alive_interval: Duration, mut notify: F) -> Body
+where
+    S: Sequencer + Send + Sync + 'static,
+    T: Serialize,
+    F: FnMut(&S, ChainNotification) -> Vec<(&'static str, T)> + Send + 'static,
+{
+    let (mut sender, body) = Body::channel();
+
+    tokio::spawn(async move {
+        let mut receiver = sequencer.read().await.subscribe_chain_notifications();
+        let mut keep_alive = tokio::time::interval(keep_alive_interval);
+
+        loop {
+            let notification = tokio::select! {
+                notification = receiver.recv() => match notification {
+                    Ok(notification) => notification,
+                    // Keep going with the most recent notifications if the client is too slow.
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                },
+                _ = keep_alive.tick() => {
+                    if sender.send_data(Bytes::from_static(b": keep-alive\n\n")).await.is_err() {
+                        break;
+                    }
+                    continue;
+                }
+            };
+
+            let events = notify(&*sequencer.read().await, notification);
+            for (name, data) in events {
+                let Ok(data) = serde_json::to_string(&data) else {
+                    continue;
+                };
+                let event = format!("event: {name}\ndata: {data}\n\n");
+                if sender.send_data(Bytes::from(event)).await.is_err() {
+                    return;
+                }
+            }
+        }
+    });
+
+    body
+}
+
+fn parse_event_filter(
+    query: Option<&str>,
+) -> Option<(Option<StarkFelt>, Option<Vec<Vec<StarkFelt>>>)> {
+    let parse_felt = |value: &str| FieldElement::from_hex_be(value).ok().map(StarkFelt::from);
+
+    let mut address = None;
+    let mut keys = Vec::new();
+
+    for (name, value) in form_urlencoded::parse(query.unwrap_or_default().as_bytes()) {
+        match name.as_ref() {
+            "from_address" => address = Some(parse_felt(&value)?),
+            "keys" => keys.push(
+                value
+                    .split(',')
+                    .filter(|key| !key.is_empty())
+                    .map(parse_felt)
+                    .collect::<Option<Vec<_>>>()?,
+            ),
+            _ => return None,
+        }
+    }
+
+    Some((address, (!keys.is_empty()).then_some(keys)))
+}
+
+fn empty_response(status: StatusCode) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::empty())
+        .expect("valid response")
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::body::HttpBody;
+    use katana_core::starknet::StarknetConfig;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::test_utils::create_sequencer;
+
+    #[test]
+    fn test_parse_event_filter() {
+        let felt = |value: u64| StarkFelt::from(value);
+
+        assert_eq!(parse_event_filter(None), Some((None, None)));
+        assert_eq!(
+            parse_event_filter(Some("from_address=0x1&keys=0x2,0x3&keys=&keys=0x4")),
+            Some((
+                Some(felt(1)),
+                Some(vec![vec![felt(2), felt(3)], vec![], vec![felt(4)]])
+            ))
+        );
+        // The parameters are percent-decoded.
+        assert_eq!(
+            parse_event_filter(Some("keys=0x2%2C0x3&from%5Faddress=0x1")),
+            Some((Some(felt(1)), Some(vec![vec![felt(2), felt(3)]])))
+        );
+
+        assert_eq!(parse_event_filter(Some("from_address=0xz")), None);
+        assert_eq!(parse_event_filter(Some("keys=0x1,nope")), None);
+        assert_eq!(parse_event_filter(Some("to_address=0x1")), None);
+    }
+
+    #[tokio::test]
+    async fn test_stream_format() {
+        let sequencer = create_sequencer(StarknetConfig::default());
+
+        let mut body = stream(
+            sequencer.clone(),
+            Duration::from_secs(3600),
+            |_, notification| {
+                let ChainNotification::NewBlock(block) = notification else {
+                    return vec![];
+                };
+                vec![("newHead", block_header(&block))]
+            },
+        );
+
+        // The first keep-alive is sent right away, once the stream is subscribed.
+        let chunk = body.data().await.unwrap().unwrap();
+        assert_eq!(chunk, Bytes::from_static(b": keep-alive\n\n"));
+
+        sequencer.write().await.generate_new_block().unwrap();
+
+        let chunk = body.data().await.unwrap().unwrap();
+        let event = std::str::from_utf8(&chunk).unwrap();
+        let data = event
+            .strip_prefix("event: newHead\ndata: ")
+            .and_then(|event| event.strip_suffix("\n\n"))
+            .unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(data).unwrap()["block_number"],
+            0
+        );
+    }
+}
@@ -3,19 +3,22 @@ use jsonrpsee::{
     proc_macros::rpc,
     types::error::{CallError, ErrorObject},
 };
+use serde::{Deserialize, Serialize};
 
 use starknet::{
     core::types::FieldElement,
     providers::jsonrpc::models::{
         BlockHashAndNumber, BlockId, BroadcastedDeclareTransaction,
         BroadcastedDeployAccountTransaction, BroadcastedInvokeTransaction, BroadcastedTransaction,
-        ContractClass, DeclareTransactionResult, DeployAccountTransactionResult, EventFilter,
-        EventsPage, FeeEstimate, FunctionCall, InvokeTransactionResult,
+        ContractClass, DeclareTransactionResult, DeployAccountTransactionResult, EmittedEvent,
+        EventFilter, EventsPage, FeeEstimate, FunctionCall, InvokeTransactionResult,
         MaybePendingBlockWithTxHashes, MaybePendingBlockWithTxs, MaybePendingTransactionReceipt,
         StateUpdate, Transaction,
     },
 };
 
+use crate::discover::MethodSpec;
+
 #[derive(thiserror::Error, Clone, Copy, Debug)]
 pub enum StarknetApiError {
     #[error("Failed to write transaction")]
@@ -64,8 +67,97 @@ impl From<StarknetApiError> for Error {
     }
 }
 
+/// A notification of `starknet_subscribePendingTransactions`, either the hash or the full body of
+/// the transaction depending on the subscription.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PendingTransaction {
+    Hash(FieldElement),
+    Full(Transaction),
+}
+
+/// A notification of `starknet_subscribeNewHeads`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockHeader {
+    pub block_hash: FieldElement,
+    pub parent_hash: FieldElement,
+    pub block_number: u64,
+    pub new_root: FieldElement,
+    pub timestamp: u64,
+    pub sequencer_address: FieldElement,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TransactionFinalityStatus {
+    Pending,
+    AcceptedOnL2,
+    Rejected,
+}
+
+/// A notification of `starknet_subscribeTransactionStatus`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionStatusUpdate {
+    pub transaction_hash: FieldElement,
+    pub finality_status: TransactionFinalityStatus,
+    /// Set if the transaction has been rejected.
+    pub failure_reason: Option<String>,
+}
+
+/// A notification of `starknet_subscribeReorg`, giving the range of the removed blocks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reorg {
+    pub starting_block_hash: FieldElement,
+    pub starting_block_number: u64,
+    pub ending_block_hash: FieldElement,
+    pub ending_block_number: u64,
+}
+
 #[rpc(server, client, namespace = "starknet")]
 pub trait StarknetApi {
+    #[subscription(
+        name = "subscribePendingTransactions",
+        unsubscribe = "unsubscribePendingTransactions",
+        item = PendingTransaction
+    )]
+    fn subscribe_pending_transactions(
+        &self,
+        sender_address: Option<Vec<FieldElement>>,
+        transaction_details: Option<bool>,
+    );
+
+    #[subscription(
+        name = "subscribeNewHeads",
+        unsubscribe = "unsubscribeNewHeads",
+        item = BlockHeader
+    )]
+    fn subscribe_new_heads(&self);
+
+    /// Notifies the events of the sealed blocks, filtered as with `starknet_getEvents`.
+    #[subscription(
+        name = "subscribeEvents",
+        unsubscribe = "unsubscribeEvents",
+        item = EmittedEvent
+    )]
+    fn subscribe_events(
+        &self,
+        from_address: Option<FieldElement>,
+        keys: Option<Vec<Vec<FieldElement>>>,
+    );
+
+    /// Notifies the status of a transaction until it is accepted or rejected.
+    #[subscription(
+        name = "subscribeTransactionStatus",
+        unsubscribe = "unsubscribeTransactionStatus",
+        item = TransactionStatusUpdate
+    )]
+    fn subscribe_transaction_status(&self, transaction_hash: FieldElement);
+
+    /// Notifies the blocks removed from the chain, which only happens when reverting to a
+    /// snapshot.
+    #[subscription(name = "subscribeReorg", unsubscribe = "unsubscribeReorg", item = Reorg)]
+    fn subscribe_reorg(&self);
+
     #[method(name = "chainId")]
     async fn chain_id(&self) -> Result<String, Error>;
 
@@ -137,6 +229,10 @@ pub trait StarknetApi {
         class_hash: FieldElement,
     ) -> Result<ContractClass, Error>;
 
+    /// Returns the CASM a Sierra class was compiled to when it was declared on this node.
+    #[method(name = "getCompiledCasm")]
+    async fn compiled_casm(&self, class_hash: FieldElement) -> Result<serde_json::Value, Error>;
+
     #[method(name = "getEvents")]
     async fn events(
         &self,
@@ -188,3 +284,167 @@ pub trait StarknetApi {
         invoke_transaction: BroadcastedInvokeTransaction,
     ) -> Result<InvokeTransactionResult, Error>;
 }
+
+/// The signatures of the methods of [`StarknetApi`], for the OpenRPC document.
+pub(crate) const METHODS: &[MethodSpec] = &[
+    MethodSpec::new(
+        "starknet_subscribePendingTransactions",
+        &[
+            ("sender_address", "Option<Vec<FieldElement>>"),
+            ("transaction_details", "Option<bool>"),
+        ],
+        "SubscriptionId",
+    ),
+    MethodSpec::new(
+        "starknet_unsubscribePendingTransactions",
+        UNSUBSCRIBE_PARAMS,
+        "bool",
+    ),
+    MethodSpec::new("starknet_subscribeNewHeads", &[], "SubscriptionId"),
+    MethodSpec::new("starknet_unsubscribeNewHeads", UNSUBSCRIBE_PARAMS, "bool"),
+    MethodSpec::new(
+        "starknet_subscribeEvents",
+        &[
+            ("from_address", "Option<FieldElement>"),
+            ("keys", "Option<Vec<Vec<FieldElement>>>"),
+        ],
+        "SubscriptionId",
+    ),
+    MethodSpec::new("starknet_unsubscribeEvents", UNSUBSCRIBE_PARAMS, "bool"),
+    MethodSpec::new(
+        "starknet_subscribeTransactionStatus",
+        &[("transaction_hash", "FieldElement")],
+        "SubscriptionId",
+    ),
+    MethodSpec::new(
+        "starknet_unsubscribeTransactionStatus",
+        UNSUBSCRIBE_PARAMS,
+        "bool",
+    ),
+    MethodSpec::new("starknet_subscribeReorg", &[], "SubscriptionId"),
+    MethodSpec::new("starknet_unsubscribeReorg", UNSUBSCRIBE_PARAMS, "bool"),
+    MethodSpec::new("starknet_chainId", &[], "String"),
+    MethodSpec::new(
+        "starknet_getNonce",
+        &[
+            ("block_id", "BlockId"),
+            ("contract_address", "FieldElement"),
+        ],
+        "FieldElement",
+    ),
+    MethodSpec::new("starknet_blockNumber", &[], "u64"),
+    MethodSpec::new(
+        "starknet_getTransactionByHash",
+        &[("transaction_hash", "FieldElement")],
+        "Transaction",
+    ),
+    MethodSpec::new(
+        "starknet_getBlockTransactionCount",
+        &[("block_id", "BlockId")],
+        "u64",
+    ),
+    MethodSpec::new(
+        "starknet_getClassAt",
+        &[
+            ("block_id", "BlockId"),
+            ("contract_address", "FieldElement"),
+        ],
+        "ContractClass",
+    ),
+    MethodSpec::new("starknet_blockHashAndNumber", &[], "BlockHashAndNumber"),
+    MethodSpec::new(
+        "starknet_getBlockWithTxHashes",
+        &[("block_id", "BlockId")],
+        "MaybePendingBlockWithTxHashes",
+    ),
+    MethodSpec::new(
+        "starknet_getTransactionByBlockIdAndIndex",
+        &[("block_id", "BlockId"), ("index", "usize")],
+        "Transaction",
+    ),
+    MethodSpec::new(
+        "starknet_getBlockWithTxs",
+        &[("block_id", "BlockId")],
+        "MaybePendingBlockWithTxs",
+    ),
+    MethodSpec::new(
+        "starknet_getStateUpdate",
+        &[("block_id", "BlockId")],
+        "StateUpdate",
+    ),
+    MethodSpec::new(
+        "starknet_getTransactionReceipt",
+        &[("transaction_hash", "FieldElement")],
+        "MaybePendingTransactionReceipt",
+    ),
+    MethodSpec::new(
+        "starknet_getClassHashAt",
+        &[
+            ("block_id", "BlockId"),
+            ("contract_address", "FieldElement"),
+        ],
+        "FieldElement",
+    ),
+    MethodSpec::new(
+        "starknet_getClass",
+        &[("block_id", "BlockId"), ("class_hash", "FieldElement")],
+        "ContractClass",
+    ),
+    MethodSpec::new(
+        "starknet_getCompiledCasm",
+        &[("class_hash", "FieldElement")],
+        "CasmContractClass",
+    ),
+    MethodSpec::new(
+        "starknet_getEvents",
+        &[
+            ("filter", "EventFilter"),
+            ("continuation_token", "Option<String>"),
+            ("chunk_size", "u64"),
+        ],
+        "EventsPage",
+    ),
+    MethodSpec::new("starknet_pendingTransactions", &[], "Vec<Transaction>"),
+    MethodSpec::new(
+        "starknet_estimateFee",
+        &[
+            ("request", "BroadcastedTransaction"),
+            ("block_id", "BlockId"),
+        ],
+        "FeeEstimate",
+    ),
+    MethodSpec::new(
+        "starknet_call",
+        &[("request", "FunctionCall"), ("block_id", "BlockId")],
+        "Vec<FieldElement>",
+    ),
+    MethodSpec::new(
+        "starknet_getStorageAt",
+        &[
+            ("contract_address", "FieldElement"),
+            ("key", "FieldElement"),
+            ("block_id", "BlockId"),
+        ],
+        "FieldElement",
+    ),
+    MethodSpec::new(
+        "starknet_addDeployAccountTransaction",
+        &[(
+            "deploy_account_transaction",
+            "BroadcastedDeployAccountTransaction",
+        )],
+        "DeployAccountTransactionResult",
+    ),
+    MethodSpec::new(
+        "starknet_addDeclareTransaction",
+        &[("transaction", "BroadcastedDeclareTransaction")],
+        "DeclareTransactionResult",
+    ),
+    MethodSpec::new(
+        "starknet_addInvokeTransaction",
+        &[("invoke_transaction", "BroadcastedInvokeTransaction")],
+        "InvokeTransactionResult",
+    ),
+];
+
+const UNSUBSCRIBE_PARAMS: &[(&str, &str)] = &[("subscription_id", "SubscriptionId")];
@@ -1,17 +1,26 @@
-use blockifier::transaction::{
-    account_transaction::AccountTransaction, transactions::DeclareTransaction,
+use blockifier::{
+    execution::contract_class::{ContractClassV0, ContractClassV1},
+    transaction::{account_transaction::AccountTransaction, transactions::DeclareTransaction},
 };
 
 use jsonrpsee::{
-    core::{async_trait, Error},
-    types::error::CallError,
+    core::{async_trait, server::rpc_module::SubscriptionSink, Error},
+    types::{error::CallError, SubscriptionResult},
 };
 use katana_core::{
     constants::SEQUENCER_ADDRESS,
     sequencer::Sequencer,
-    starknet::transaction::ExternalFunctionCall,
-    util::{blockifier_contract_class_from_flattened_sierra_class, starkfelt_to_u128},
+    starknet::{
+        block::StarknetBlock, event::EventOrder, notification::ChainNotification,
+        transaction::ExternalFunctionCall,
+    },
+    util::{
+        blockifier_contract_class_from_flattened_sierra_class,
+        casm_contract_class_from_flattened_sierra_class, starkfelt_to_u128,
+    },
 };
+use serde::Serialize;
+use starknet::core::types::TransactionStatus;
 use starknet::providers::jsonrpc::models::{
     BlockHashAndNumber, BlockId, BlockStatus, BlockWithTxHashes, BlockWithTxs,
     BroadcastedDeclareTransaction, BroadcastedDeployAccountTransaction,
@@ -26,8 +35,8 @@ use starknet_api::{
     core::{ClassHash, CompiledClassHash, ContractAddress, PatriciaKey},
     hash::StarkFelt,
     transaction::{
-        Calldata, ContractAddressSalt, DeclareTransactionV2, Fee, InvokeTransaction,
-        TransactionVersion,
+        Calldata, ContractAddressSalt, DeclareTransactionV0V1, DeclareTransactionV2, Fee,
+        InvokeTransaction, TransactionVersion,
     },
 };
 use starknet_api::{
@@ -38,30 +47,224 @@ use starknet_api::{
 use starknet_api::{hash::StarkHash, transaction::TransactionSignature};
 use starknet_api::{state::StorageKey, transaction::InvokeTransactionV1};
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use utils::transaction::{
-    compute_declare_v2_transaction_hash, compute_invoke_v1_transaction_hash,
-    convert_inner_to_rpc_tx,
+use tokio::sync::{broadcast::error::RecvError, RwLock};
+use utils::{
+    contract::{decompress_legacy_contract_class, get_legacy_contract_class_hash},
+    transaction::{
+        compute_declare_v1_transaction_hash, compute_declare_v2_transaction_hash,
+        compute_invoke_v1_transaction_hash, convert_inner_to_rpc_tx,
+    },
 };
 
-use crate::utils;
+use crate::{
+    audit::{caller_ip, AuditLog},
+    katana::{api::KatanaApiError, declare_error, ensure_not_frozen},
+    utils,
+};
 
-use self::api::{StarknetApiError, StarknetApiServer};
+use self::api::{
+    BlockHeader, PendingTransaction, Reorg, StarknetApiError, StarknetApiServer,
+    TransactionFinalityStatus, TransactionStatusUpdate,
+};
 
 pub mod api;
 
+/// The maximum size of a decompressed legacy program accepted by `starknet_addDeclareTransaction`,
+/// matching the maximum size of a request body.
+const MAX_DECOMPRESSED_PROGRAM_SIZE: u64 = 10 * 1024 * 1024;
+
 pub struct StarknetRpc<S> {
     sequencer: Arc<RwLock<S>>,
+    audit_log: Arc<AuditLog>,
 }
 
 impl<S: Sequencer + Send + Sync + 'static> StarknetRpc<S> {
-    pub fn new(sequencer: Arc<RwLock<S>>) -> Self {
-        Self { sequencer }
+    pub fn new(sequencer: Arc<RwLock<S>>, audit_log: Arc<AuditLog>) -> Self {
+        Self {
+            sequencer,
+            audit_log,
+        }
     }
 }
 #[allow(unused)]
 #[async_trait]
 impl<S: Sequencer + Send + Sync + 'static> StarknetApiServer for StarknetRpc<S> {
+    fn subscribe_pending_transactions(
+        &self,
+        mut sink: SubscriptionSink,
+        sender_address: Option<Vec<FieldElement>>,
+        transaction_details: Option<bool>,
+    ) -> SubscriptionResult {
+        let sequencer = self.sequencer.clone();
+        let transaction_details = transaction_details.unwrap_or(false);
+        let sender_address = sender_address.map(|addresses| {
+            addresses
+                .into_iter()
+                .map(|address| ContractAddress(patricia_key!(address)))
+                .collect::<Vec<_>>()
+        });
+
+        tokio::spawn(async move {
+            let mut receiver = sequencer.read().await.subscribe_pending_transactions();
+
+            if sink.accept().is_err() {
+                return;
+            }
+
+            loop {
+                let transaction = match receiver.recv().await {
+                    Ok(transaction) => transaction,
+                    // Keep going with the most recent transactions if the subscriber is too slow.
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                };
+
+                if let Some(ref addresses) = sender_address {
+                    match transaction_sender(&transaction) {
+                        Some(sender) if addresses.contains(&sender) => {}
+                        _ => continue,
+                    }
+                }
+
+                let notification = if transaction_details {
+                    match convert_inner_to_rpc_tx(transaction) {
+                        Ok(transaction) => PendingTransaction::Full(transaction),
+                        Err(_) => continue,
+                    }
+                } else {
+                    PendingTransaction::Hash(transaction.transaction_hash().0.into())
+                };
+
+                if !matches!(sink.send(&notification), Ok(true)) {
+                    break;
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn subscribe_new_heads(&self, sink: SubscriptionSink) -> SubscriptionResult {
+        spawn_chain_subscription(self.sequencer.clone(), sink, |_, notification| {
+            let ChainNotification::NewBlock(block) = notification else {
+                return vec![];
+            };
+
+            vec![block_header(&block)]
+        });
+
+        Ok(())
+    }
+
+    fn subscribe_events(
+        &self,
+        sink: SubscriptionSink,
+        from_address: Option<FieldElement>,
+        keys: Option<Vec<Vec<FieldElement>>>,
+    ) -> SubscriptionResult {
+        let address = from_address.map(StarkFelt::from);
+        let keys = keys.map(|keys| {
+            keys.iter()
+                .map(|key| key.iter().map(|key| (*key).into()).collect())
+                .collect::<Vec<_>>()
+        });
+
+        spawn_chain_subscription(
+            self.sequencer.clone(),
+            sink,
+            move |sequencer, notification| {
+                let ChainNotification::NewBlock(block) = notification else {
+                    return vec![];
+                };
+
+                block_events(sequencer, &block, address, keys.clone())
+            },
+        );
+
+        Ok(())
+    }
+
+    fn subscribe_transaction_status(
+        &self,
+        mut sink: SubscriptionSink,
+        transaction_hash: FieldElement,
+    ) -> SubscriptionResult {
+        let sequencer = self.sequencer.clone();
+        let hash = TransactionHash(StarkFelt::from(transaction_hash));
+
+        tokio::spawn(async move {
+            let mut receiver = sequencer.read().await.subscribe_chain_notifications();
+
+            if sink.accept().is_err() {
+                return;
+            }
+
+            let mut last_status = None;
+
+            loop {
+                let update = {
+                    let sequencer = sequencer.read().await;
+                    sequencer
+                        .transaction_status(&hash)
+                        .and_then(finality_status)
+                        .map(|status| TransactionStatusUpdate {
+                            transaction_hash,
+                            finality_status: status,
+                            failure_reason: sequencer.rejected_transaction_reason(&hash),
+                        })
+                };
+
+                if let Some(update) = update {
+                    if last_status != Some(update.finality_status) {
+                        last_status = Some(update.finality_status);
+                        if !matches!(sink.send(&update), Ok(true)) {
+                            break;
+                        }
+                    }
+                }
+
+                if matches!(
+                    last_status,
+                    Some(
+                        TransactionFinalityStatus::AcceptedOnL2
+                            | TransactionFinalityStatus::Rejected
+                    )
+                ) {
+                    break;
+                }
+
+                // The status is checked again once a new block is sealed.
+                match receiver.recv().await {
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn subscribe_reorg(&self, sink: SubscriptionSink) -> SubscriptionResult {
+        spawn_chain_subscription(self.sequencer.clone(), sink, |_, notification| {
+            let ChainNotification::Reorg {
+                starting_block: (starting_block_hash, starting_block_number),
+                ending_block: (ending_block_hash, ending_block_number),
+            } = notification
+            else {
+                return vec![];
+            };
+
+            vec![Reorg {
+                starting_block_hash: starting_block_hash.0.into(),
+                starting_block_number: starting_block_number.0,
+                ending_block_hash: ending_block_hash.0.into(),
+                ending_block_number: ending_block_number.0,
+            }]
+        });
+
+        Ok(())
+    }
+
     async fn chain_id(&self) -> Result<String, Error> {
         Ok(self.sequencer.read().await.chain_id().as_hex())
     }
@@ -73,7 +276,7 @@ impl<S: Sequencer + Send + Sync + 'static> StarknetApiServer for StarknetRpc<S>
     ) -> Result<FieldElement, Error> {
         let nonce = self
             .sequencer
-            .write()
+            .read()
             .await
             .nonce_at(block_id, ContractAddress(patricia_key!(contract_address)))
             .map_err(|_| Error::from(StarknetApiError::ContractError))?;
@@ -91,7 +294,7 @@ impl<S: Sequencer + Send + Sync + 'static> StarknetApiServer for StarknetRpc<S>
     ) -> Result<Transaction, Error> {
         let tx = self
             .sequencer
-            .write()
+            .read()
             .await
             .transaction(&TransactionHash(StarkFelt::from(transaction_hash)))
             .ok_or(Error::from(StarknetApiError::TxnHashNotFound))?;
@@ -263,7 +466,7 @@ impl<S: Sequencer + Send + Sync + 'static> StarknetApiServer for StarknetRpc<S>
     ) -> Result<FieldElement, Error> {
         let class_hash = self
             .sequencer
-            .write()
+            .read()
             .await
             .class_hash_at(block_id, ContractAddress(patricia_key!(contract_address)))
             .map_err(|_| Error::from(StarknetApiError::ContractError))?;
@@ -279,6 +482,18 @@ impl<S: Sequencer + Send + Sync + 'static> StarknetApiServer for StarknetRpc<S>
         Err(Error::from(StarknetApiError::InternalServerError))
     }
 
+    async fn compiled_casm(&self, class_hash: FieldElement) -> Result<serde_json::Value, Error> {
+        // Legacy classes aren't compiled, so there is no CASM to return for them either.
+        let casm = self
+            .sequencer
+            .read()
+            .await
+            .compiled_casm(ClassHash(StarkFelt::from(class_hash)))
+            .ok_or(Error::from(StarknetApiError::ClassHashNotFound))?;
+
+        Ok(serde_json::to_value(casm)?)
+    }
+
     async fn events(
         &self,
         filter: EventFilter,
@@ -307,30 +522,7 @@ impl<S: Sequencer + Send + Sync + 'static> StarknetApiServer for StarknetRpc<S>
             .map_err(|_| Error::from(StarknetApiError::InternalServerError))?;
 
         Ok(EventsPage {
-            events: events
-                .iter()
-                .map(|e| EmittedEvent {
-                    block_number: e.block_number.0,
-                    block_hash: (e.block_hash.0).into(),
-                    transaction_hash: (e.transaction_hash.0).into(),
-                    from_address: (*e.inner.from_address.0.key()).into(),
-                    keys: e
-                        .inner
-                        .content
-                        .keys
-                        .iter()
-                        .map(|key| (key.0).into())
-                        .collect(),
-                    data: e
-                        .inner
-                        .content
-                        .data
-                        .0
-                        .iter()
-                        .map(|fe| (*fe).into())
-                        .collect(),
-                })
-                .collect(),
+            events: events.iter().map(emitted_event_to_rpc_event).collect(),
             continuation_token: None,
         })
     }
@@ -376,7 +568,7 @@ impl<S: Sequencer + Send + Sync + 'static> StarknetApiServer for StarknetRpc<S>
     ) -> Result<FieldElement, Error> {
         let value = self
             .sequencer
-            .write()
+            .read()
             .await
             .storage_at(
                 ContractAddress(patricia_key!(contract_address)),
@@ -392,6 +584,8 @@ impl<S: Sequencer + Send + Sync + 'static> StarknetApiServer for StarknetRpc<S>
         &self,
         deploy_account_transaction: BroadcastedDeployAccountTransaction,
     ) -> Result<DeployAccountTransactionResult, Error> {
+        ensure_not_frozen(&*self.sequencer.read().await)?;
+
         let BroadcastedDeployAccountTransaction {
             max_fee,
             version,
@@ -420,9 +614,20 @@ impl<S: Sequencer + Send + Sync + 'static> StarknetApiServer for StarknetRpc<S>
             )
             .map_err(|e| Error::Call(CallError::Failed(anyhow::anyhow!(e.to_string()))))?;
 
+        let transaction_hash = FieldElement::from(transaction_hash.0);
+        let contract_address = FieldElement::from(*contract_address.0.key());
+
+        self.audit_log.record_transaction(
+            "starknet_addDeployAccountTransaction",
+            caller_ip(),
+            contract_address,
+            transaction_hash,
+            is_accepted(&*self.sequencer.read().await, transaction_hash),
+        );
+
         Ok(DeployAccountTransactionResult {
-            transaction_hash: FieldElement::from(transaction_hash.0),
-            contract_address: FieldElement::from(*contract_address.0.key()),
+            transaction_hash,
+            contract_address,
         })
     }
 
@@ -434,82 +639,7 @@ impl<S: Sequencer + Send + Sync + 'static> StarknetApiServer for StarknetRpc<S>
         let chain_id = FieldElement::from_hex_be(&self.sequencer.read().await.chain_id().as_hex())
             .map_err(|_| Error::from(StarknetApiError::InternalServerError))?;
 
-        let transaction = match request {
-            BroadcastedTransaction::Declare(BroadcastedDeclareTransaction::V2(tx)) => {
-                let raw_class_str = serde_json::to_string(&tx.contract_class)?;
-                let class_hash = serde_json::from_str::<FlattenedSierraClass>(&raw_class_str)
-                    .map_err(|_| Error::from(StarknetApiError::InvalidContractClass))?
-                    .class_hash();
-                let contract_class =
-                    blockifier_contract_class_from_flattened_sierra_class(&raw_class_str)
-                        .map_err(|_| Error::from(StarknetApiError::InternalServerError))?;
-
-                let transaction_hash = compute_declare_v2_transaction_hash(
-                    tx.sender_address,
-                    class_hash,
-                    tx.max_fee,
-                    chain_id,
-                    tx.nonce,
-                    tx.compiled_class_hash,
-                );
-
-                let transaction = DeclareTransactionV2 {
-                    transaction_hash: TransactionHash(StarkFelt::from(transaction_hash)),
-                    class_hash: ClassHash(StarkFelt::from(class_hash)),
-                    sender_address: ContractAddress(patricia_key!(tx.sender_address)),
-                    nonce: Nonce(StarkFelt::from(tx.nonce)),
-                    max_fee: Fee(starkfelt_to_u128(StarkFelt::from(tx.max_fee))
-                        .map_err(|_| Error::from(StarknetApiError::InternalServerError))?),
-                    signature: TransactionSignature(
-                        tx.signature.into_iter().map(StarkFelt::from).collect(),
-                    ),
-                    compiled_class_hash: CompiledClassHash(StarkFelt::from(tx.compiled_class_hash)),
-                };
-
-                AccountTransaction::Declare(DeclareTransaction {
-                    tx: starknet_api::transaction::DeclareTransaction::V2(transaction),
-                    contract_class: blockifier::execution::contract_class::ContractClass::V1(
-                        contract_class,
-                    ),
-                })
-            }
-
-            BroadcastedTransaction::Invoke(BroadcastedInvokeTransaction::V1(transaction)) => {
-                let transaction_hash = compute_invoke_v1_transaction_hash(
-                    transaction.sender_address,
-                    &transaction.calldata,
-                    transaction.max_fee,
-                    chain_id,
-                    transaction.nonce,
-                );
-
-                let transaction = InvokeTransactionV1 {
-                    transaction_hash: TransactionHash(StarkFelt::from(transaction_hash)),
-                    sender_address: ContractAddress(patricia_key!(transaction.sender_address)),
-                    nonce: Nonce(StarkFelt::from(transaction.nonce)),
-                    calldata: Calldata(Arc::new(
-                        transaction
-                            .calldata
-                            .into_iter()
-                            .map(StarkFelt::from)
-                            .collect(),
-                    )),
-                    max_fee: Fee(starkfelt_to_u128(StarkFelt::from(transaction.max_fee))
-                        .map_err(|_| Error::from(StarknetApiError::InternalServerError))?),
-                    signature: TransactionSignature(
-                        transaction
-                            .signature
-                            .into_iter()
-                            .map(StarkFelt::from)
-                            .collect(),
-                    ),
-                };
-
-                AccountTransaction::Invoke(InvokeTransaction::V1(transaction))
-            }
-
-            _ => return Err(Error::from(StarknetApiError::InternalServerError)),
-        };
+        let transaction = broadcasted_to_account_transaction(request, chain_id)?;
 
         let fee_estimate = self
             .sequencer
@@ -529,61 +659,139 @@ impl<S: Sequencer + Send + Sync + 'static> StarknetApiServer for StarknetRpc<S>
         &self,
         transaction: BroadcastedDeclareTransaction,
     ) -> Result<DeclareTransactionResult, Error> {
+        ensure_not_frozen(&*self.sequencer.read().await)?;
+
         let chain_id = FieldElement::from_hex_be(&self.sequencer.read().await.chain_id().as_hex())
             .map_err(|_| Error::from(StarknetApiError::InternalServerError))?;
 
-        let (transaction_hash, class_hash, transaction) = match transaction {
-            BroadcastedDeclareTransaction::V1(_) => {
-                return Err(Error::from(StarknetApiError::InternalServerError))
-            }
-            BroadcastedDeclareTransaction::V2(tx) => {
-                let raw_class_str = serde_json::to_string(&tx.contract_class)?;
-                let class_hash = serde_json::from_str::<FlattenedSierraClass>(&raw_class_str)
-                    .map_err(|_| Error::from(StarknetApiError::InvalidContractClass))?
-                    .class_hash();
-                let contract_class =
-                    blockifier_contract_class_from_flattened_sierra_class(&raw_class_str)
-                        .map_err(|_| Error::from(StarknetApiError::InternalServerError))?;
-
-                let transaction_hash = compute_declare_v2_transaction_hash(
-                    tx.sender_address,
-                    class_hash,
-                    tx.max_fee,
-                    chain_id,
-                    tx.nonce,
-                    tx.compiled_class_hash,
-                );
-
-                let transaction = DeclareTransactionV2 {
-                    transaction_hash: TransactionHash(StarkFelt::from(transaction_hash)),
-                    class_hash: ClassHash(StarkFelt::from(class_hash)),
-                    sender_address: ContractAddress(patricia_key!(tx.sender_address)),
-                    nonce: Nonce(StarkFelt::from(tx.nonce)),
-                    max_fee: Fee(starkfelt_to_u128(StarkFelt::from(tx.max_fee))
-                        .map_err(|_| Error::from(StarknetApiError::InternalServerError))?),
-                    signature: TransactionSignature(
-                        tx.signature.into_iter().map(StarkFelt::from).collect(),
-                    ),
-                    compiled_class_hash: CompiledClassHash(StarkFelt::from(tx.compiled_class_hash)),
-                };
-
-                (
-                    transaction_hash,
-                    class_hash,
-                    AccountTransaction::Declare(DeclareTransaction {
-                        tx: starknet_api::transaction::DeclareTransaction::V2(transaction),
-                        contract_class: blockifier::execution::contract_class::ContractClass::V1(
-                            contract_class,
+        let (sender_address, transaction_hash, class_hash, abi, casm, transaction) =
+            match transaction {
+                BroadcastedDeclareTransaction::V1(tx) => {
+                    let raw_class_str = decompress_legacy_contract_class(
+                        &serde_json::to_string(&tx.contract_class)?,
+                        MAX_DECOMPRESSED_PROGRAM_SIZE,
+                    )
+                    .map_err(|_| Error::from(StarknetApiError::InvalidContractClass))?;
+                    let class_hash = get_legacy_contract_class_hash(&raw_class_str)
+                        .map_err(|_| Error::from(StarknetApiError::InvalidContractClass))?;
+                    let contract_class = serde_json::from_str::<ContractClassV0>(&raw_class_str)
+                        .map_err(|_| Error::from(StarknetApiError::InvalidContractClass))?;
+                    let abi = tx
+                        .contract_class
+                        .abi
+                        .as_ref()
+                        .map(serde_json::to_string)
+                        .transpose()?;
+
+                    let transaction_hash = compute_declare_v1_transaction_hash(
+                        tx.sender_address,
+                        class_hash,
+                        tx.max_fee,
+                        chain_id,
+                        tx.nonce,
+                    );
+
+                    let transaction = DeclareTransactionV0V1 {
+                        transaction_hash: TransactionHash(StarkFelt::from(transaction_hash)),
+                        class_hash: ClassHash(StarkFelt::from(class_hash)),
+                        sender_address: ContractAddress(patricia_key!(tx.sender_address)),
+                        nonce: Nonce(StarkFelt::from(tx.nonce)),
+                        max_fee: Fee(starkfelt_to_u128(StarkFelt::from(tx.max_fee))
+                            .map_err(|_| Error::from(StarknetApiError::InternalServerError))?),
+                        signature: TransactionSignature(
+                            tx.signature.into_iter().map(StarkFelt::from).collect(),
                         ),
-                    }),
-                )
-            }
-        };
+                    };
+
+                    (
+                        tx.sender_address,
+                        transaction_hash,
+                        class_hash,
+                        abi,
+                        None,
+                        DeclareTransaction {
+                            tx: starknet_api::transaction::DeclareTransaction::V1(transaction),
+                            contract_class:
+                                blockifier::execution::contract_class::ContractClass::V0(
+                                    contract_class,
+                                ),
+                        },
+                    )
+                }
+                BroadcastedDeclareTransaction::V2(tx) => {
+                    let raw_class_str = serde_json::to_string(&tx.contract_class)?;
+                    let sierra_class = serde_json::from_str::<FlattenedSierraClass>(&raw_class_str)
+                        .map_err(|_| Error::from(StarknetApiError::InvalidContractClass))?;
+                    let class_hash = sierra_class.class_hash();
+                    let casm = casm_contract_class_from_flattened_sierra_class(&raw_class_str)
+                        .map_err(|_| Error::from(StarknetApiError::InvalidContractClass))?;
+                    let contract_class = ContractClassV1::try_from(casm.clone())
+                        .map_err(|_| Error::from(StarknetApiError::InvalidContractClass))?;
+
+                    let transaction_hash = compute_declare_v2_transaction_hash(
+                        tx.sender_address,
+                        class_hash,
+                        tx.max_fee,
+                        chain_id,
+                        tx.nonce,
+                        tx.compiled_class_hash,
+                    );
+
+                    let transaction = DeclareTransactionV2 {
+                        transaction_hash: TransactionHash(StarkFelt::from(transaction_hash)),
+                        class_hash: ClassHash(StarkFelt::from(class_hash)),
+                        sender_address: ContractAddress(patricia_key!(tx.sender_address)),
+                        nonce: Nonce(StarkFelt::from(tx.nonce)),
+                        max_fee: Fee(starkfelt_to_u128(StarkFelt::from(tx.max_fee))
+                            .map_err(|_| Error::from(StarknetApiError::InternalServerError))?),
+                        signature: TransactionSignature(
+                            tx.signature.into_iter().map(StarkFelt::from).collect(),
+                        ),
+                        compiled_class_hash: CompiledClassHash(StarkFelt::from(
+                            tx.compiled_class_hash,
+                        )),
+                    };
+
+                    (
+                        tx.sender_address,
+                        transaction_hash,
+                        class_hash,
+                        Some(sierra_class.abi),
+                        Some(casm),
+                        DeclareTransaction {
+                            tx: starknet_api::transaction::DeclareTransaction::V2(transaction),
+                            contract_class:
+                                blockifier::execution::contract_class::ContractClass::V1(
+                                    contract_class,
+                                ),
+                        },
+                    )
+                }
+            };
+
+        if !self
+            .sequencer
+            .read()
+            .await
+            .declare_policy()
+            .allows(&ClassHash(StarkFelt::from(class_hash)))
+        {
+            return Err(Error::from(KatanaApiError::ClassNotDeclarable));
+        }
 
         self.sequencer
             .write()
             .await
-            .add_account_transaction(transaction);
+            .add_declare_transaction(transaction, abi, casm)
+            .map_err(declare_error)?;
+
+        self.audit_log.record_transaction(
+            "starknet_addDeclareTransaction",
+            caller_ip(),
+            sender_address,
+            transaction_hash,
+            is_accepted(&*self.sequencer.read().await, transaction_hash),
+        );
 
         Ok(DeclareTransactionResult {
             transaction_hash,
@@ -595,8 +803,11 @@ impl<S: Sequencer + Send + Sync + 'static> StarknetApiServer for StarknetRpc<S>
         &self,
         invoke_transaction: BroadcastedInvokeTransaction,
     ) -> Result<InvokeTransactionResult, Error> {
+        ensure_not_frozen(&*self.sequencer.read().await)?;
+
         match invoke_transaction {
             BroadcastedInvokeTransaction::V1(transaction) => {
+                let sender_address = transaction.sender_address;
                 let chain_id =
                     FieldElement::from_hex_be(&self.sequencer.read().await.chain_id().as_hex())
                         .map_err(|_| Error::from(StarknetApiError::InternalServerError))?;
@@ -636,7 +847,16 @@ impl<S: Sequencer + Send + Sync + 'static> StarknetApiServer for StarknetRpc<S>
                     .await
                     .add_account_transaction(AccountTransaction::Invoke(InvokeTransaction::V1(
                         transaction,
-                    )));
+                    )))
+                    .map_err(|_| Error::from(StarknetApiError::InternalServerError))?;
+
+                self.audit_log.record_transaction(
+                    "starknet_addInvokeTransaction",
+                    caller_ip(),
+                    sender_address,
+                    transaction_hash,
+                    is_accepted(&*self.sequencer.read().await, transaction_hash),
+                );
 
                 Ok(InvokeTransactionResult { transaction_hash })
             }
@@ -645,3 +865,285 @@ impl<S: Sequencer + Send + Sync + 'static> StarknetApiServer for StarknetRpc<S>
         }
     }
 }
+
+// Whether the sequencer accepted the transaction, as rejected transactions are still stored.
+fn is_accepted<S: Sequencer>(sequencer: &S, transaction_hash: FieldElement) -> bool {
+    sequencer.transaction_status(&TransactionHash(StarkFelt::from(transaction_hash)))
+        != Some(TransactionStatus::Rejected)
+}
+
+pub(crate) fn broadcasted_to_account_transaction(
+    transaction: BroadcastedTransaction,
+    chain_id: FieldElement,
+) -> Result<AccountTransaction, Error> {
+    let transaction = match transaction {
+        BroadcastedTransaction::Declare(BroadcastedDeclareTransaction::V2(tx)) => {
+            let raw_class_str = serde_json::to_string(&tx.contract_class)?;
+            let class_hash = serde_json::from_str::<FlattenedSierraClass>(&raw_class_str)
+                .map_err(|_| Error::from(StarknetApiError::InvalidContractClass))?
+                .class_hash();
+            let contract_class =
+                blockifier_contract_class_from_flattened_sierra_class(&raw_class_str)
+                    .map_err(|_| Error::from(StarknetApiError::InternalServerError))?;
+
+            let transaction_hash = compute_declare_v2_transaction_hash(
+                tx.sender_address,
+                class_hash,
+                tx.max_fee,
+                chain_id,
+                tx.nonce,
+                tx.compiled_class_hash,
+            );
+
+            let transaction = DeclareTransactionV2 {
+                transaction_hash: TransactionHash(StarkFelt::from(transaction_hash)),
+                class_hash: ClassHash(StarkFelt::from(class_hash)),
+                sender_address: ContractAddress(patricia_key!(tx.sender_address)),
+                nonce: Nonce(StarkFelt::from(tx.nonce)),
+                max_fee: Fee(starkfelt_to_u128(StarkFelt::from(tx.max_fee))
+                    .map_err(|_| Error::from(StarknetApiError::InternalServerError))?),
+                signature: TransactionSignature(
+                    tx.signature.into_iter().map(StarkFelt::from).collect(),
+                ),
+                compiled_class_hash: CompiledClassHash(StarkFelt::from(tx.compiled_class_hash)),
+            };
+
+            AccountTransaction::Declare(DeclareTransaction {
+                tx: starknet_api::transaction::DeclareTransaction::V2(transaction),
+                contract_class: blockifier::execution::contract_class::ContractClass::V1(
+                    contract_class,
+                ),
+            })
+        }
+
+        BroadcastedTransaction::Invoke(BroadcastedInvokeTransaction::V1(transaction)) => {
+            let transaction_hash = compute_invoke_v1_transaction_hash(
+                transaction.sender_address,
+                &transaction.calldata,
+                transaction.max_fee,
+                chain_id,
+                transaction.nonce,
+            );
+
+            let transaction = InvokeTransactionV1 {
+                transaction_hash: TransactionHash(StarkFelt::from(transaction_hash)),
+                sender_address: ContractAddress(patricia_key!(transaction.sender_address)),
+                nonce: Nonce(StarkFelt::from(transaction.nonce)),
+                calldata: Calldata(Arc::new(
+                    transaction
+                        .calldata
+                        .into_iter()
+                        .map(StarkFelt::from)
+                        .collect(),
+                )),
+                max_fee: Fee(starkfelt_to_u128(StarkFelt::from(transaction.max_fee))
+                    .map_err(|_| Error::from(StarknetApiError::InternalServerError))?),
+                signature: TransactionSignature(
+                    transaction
+                        .signature
+                        .into_iter()
+                        .map(StarkFelt::from)
+                        .collect(),
+                ),
+            };
+
+            AccountTransaction::Invoke(InvokeTransaction::V1(transaction))
+        }
+
+        _ => return Err(Error::from(StarknetApiError::InternalServerError)),
+    };
+
+    Ok(transaction)
+}
+
+// Accepts the subscription and sends it the items produced by `notify` from each chain notification,
+// until the subscriber goes away.
+fn spawn_chain_subscription<S, T, F>(
+    sequencer: Arc<RwLock<S>>,
+    mut sink: SubscriptionSink,
+    mut notify: F,
+) where
+    S: Sequencer + Send + Sync + 'static,
+    T: Serialize,
+    F: FnMut(&S, ChainNotification) -> Vec<T> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut receiver = sequencer.read().await.subscribe_chain_notifications();
+
+        if sink.accept().is_err() {
+            return;
+        }
+
+        loop {
+            let notification = match receiver.recv().await {
+                Ok(notification) => notification,
+                // Keep going with the most recent notifications if the subscriber is too slow.
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            };
+
+            let items = notify(&*sequencer.read().await, notification);
+            for item in items {
+                if !matches!(sink.send(&item), Ok(true)) {
+                    return;
+                }
+            }
+        }
+    });
+}
+
+pub(crate) fn block_header(block: &StarknetBlock) -> BlockHeader {
+    BlockHeader {
+        block_hash: block.block_hash().0.into(),
+        parent_hash: block.header().parent_hash.0.into(),
+        block_number: block.block_number().0,
+        new_root: block.header().state_root.0.into(),
+        timestamp: block.header().timestamp.0,
+        sequencer_address: (*block.header().sequencer.0.key()).into(),
+    }
+}
+
+/// The events emitted in a sealed block which match the given filter.
+pub(crate) fn block_events<S: Sequencer>(
+    sequencer: &S,
+    block: &StarknetBlock,
+    address: Option<StarkFelt>,
+    keys: Option<Vec<Vec<StarkFelt>>>,
+) -> Vec<EmittedEvent> {
+    sequencer
+        .events_page(
+            block.block_number(),
+            block.block_number(),
+            address,
+            keys,
+            EventOrder::Ascending,
+            None,
+            u64::MAX,
+        )
+        .map(|(events, _)| events.iter().map(emitted_event_to_rpc_event).collect())
+        .unwrap_or_default()
+}
+
+fn finality_status(status: TransactionStatus) -> Option<TransactionFinalityStatus> {
+    match status {
+        TransactionStatus::Pending => Some(TransactionFinalityStatus::Pending),
+        TransactionStatus::AcceptedOnL2 => Some(TransactionFinalityStatus::AcceptedOnL2),
+        TransactionStatus::Rejected => Some(TransactionFinalityStatus::Rejected),
+        _ => None,
+    }
+}
+
+fn transaction_sender(
+    transaction: &starknet_api::transaction::Transaction,
+) -> Option<ContractAddress> {
+    match transaction {
+        starknet_api::transaction::Transaction::Invoke(tx) => Some(tx.sender_address()),
+        starknet_api::transaction::Transaction::Declare(tx) => Some(match tx {
+            starknet_api::transaction::DeclareTransaction::V0(tx)
+            | starknet_api::transaction::DeclareTransaction::V1(tx) => tx.sender_address,
+            starknet_api::transaction::DeclareTransaction::V2(tx) => tx.sender_address,
+        }),
+        starknet_api::transaction::Transaction::DeployAccount(tx) => Some(tx.contract_address),
+        starknet_api::transaction::Transaction::Deploy(_)
+        | starknet_api::transaction::Transaction::L1Handler(_) => None,
+    }
+}
+
+pub(crate) fn emitted_event_to_rpc_event(
+    event: &katana_core::starknet::event::EmittedEvent,
+) -> EmittedEvent {
+    EmittedEvent {
+        block_number: event.block_number.0,
+        block_hash: (event.block_hash.0).into(),
+        transaction_hash: (event.transaction_hash.0).into(),
+        from_address: (*event.inner.from_address.0.key()).into(),
+        keys: event
+            .inner
+            .content
+            .keys
+            .iter()
+            .map(|key| (key.0).into())
+            .collect(),
+        data: event
+            .inner
+            .content
+            .data
+            .0
+            .iter()
+            .map(|fe| (*fe).into())
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use katana_core::{constants::ERC20_CONTRACT_CLASS_HASH, starknet::StarknetConfig};
+    use starknet::providers::jsonrpc::models::{
+        BroadcastedDeclareTransactionV2, SierraContractClass,
+    };
+
+    use super::*;
+    use crate::{
+        dev::api::DevApiServer,
+        test_utils::{create_starknet_rpcs, error_code, test_contract_class},
+    };
+
+    #[tokio::test]
+    async fn test_compiled_casm() {
+        let (starknet, dev) = create_starknet_rpcs(StarknetConfig::default());
+        let contract_class = test_contract_class();
+        let class_hash = contract_class.class_hash();
+
+        dev.deploy_contract(contract_class.clone(), vec![], FieldElement::ONE)
+            .await
+            .unwrap();
+
+        let casm = casm_contract_class_from_flattened_sierra_class(
+            &serde_json::to_string(&contract_class).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            starknet.compiled_casm(class_hash).await.unwrap(),
+            serde_json::to_value(casm).unwrap()
+        );
+
+        // Legacy classes, such as the fee token one, and unknown classes have no CASM.
+        for class_hash in [
+            FieldElement::from(*ERC20_CONTRACT_CLASS_HASH),
+            FieldElement::from(0xdeadu64),
+        ] {
+            assert_eq!(
+                error_code(starknet.compiled_casm(class_hash).await.unwrap_err()),
+                Some(StarknetApiError::ClassHashNotFound as i32)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_declare_uncompilable_class() {
+        let (starknet, _) = create_starknet_rpcs(StarknetConfig::default());
+
+        let mut contract_class = test_contract_class();
+        contract_class.sierra_program = vec![FieldElement::ONE];
+        let contract_class: SierraContractClass =
+            serde_json::from_str(&serde_json::to_string(&contract_class).unwrap()).unwrap();
+
+        let err = starknet
+            .add_declare_transaction(BroadcastedDeclareTransaction::V2(
+                BroadcastedDeclareTransactionV2 {
+                    max_fee: FieldElement::ZERO,
+                    nonce: FieldElement::ZERO,
+                    sender_address: FieldElement::ONE,
+                    signature: vec![],
+                    compiled_class_hash: FieldElement::ZERO,
+                    contract_class,
+                },
+            ))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error_code(err),
+            Some(StarknetApiError::InvalidContractClass as i32)
+        );
+    }
+}
@@ -0,0 +1,87 @@
+//! Fixtures shared by the unit tests of the RPC servers.
+
+use std::{fs, path::PathBuf, sync::Arc};
+
+use jsonrpsee::{core::Error, types::error::CallError};
+use katana_core::{
+    constants::TEST_ACCOUNT_CONTRACT_PATH, sequencer::KatanaSequencer, starknet::StarknetConfig,
+};
+use starknet::core::types::contract::{FlattenedSierraClass, SierraClass};
+use tokio::sync::RwLock;
+
+use crate::{audit::AuditLog, dev::DevRpc, katana::KatanaRpc, starknet::StarknetRpc};
+
+pub(crate) fn create_sequencer(config: StarknetConfig) -> Arc<RwLock<KatanaSequencer>> {
+    let mut sequencer = KatanaSequencer::new(config).unwrap();
+    sequencer.start();
+    Arc::new(RwLock::new(sequencer))
+}
+
+/// An audit log which records nothing.
+pub(crate) fn create_audit_log() -> Arc<AuditLog> {
+    Arc::new(AuditLog::new(None).unwrap())
+}
+
+pub(crate) fn create_dev_rpc(config: StarknetConfig) -> DevRpc<KatanaSequencer> {
+    DevRpc::new(create_sequencer(config), create_audit_log(), None)
+}
+
+/// Creates a server of the `katana` namespace, along with a `dev` one for the same sequencer.
+pub(crate) fn create_katana_rpcs(
+    config: StarknetConfig,
+) -> (KatanaRpc<KatanaSequencer>, DevRpc<KatanaSequencer>) {
+    let sequencer = create_sequencer(config);
+    let audit_log = create_audit_log();
+
+    (
+        KatanaRpc::new(sequencer.clone(), audit_log.clone()),
+        DevRpc::new(sequencer, audit_log, None),
+    )
+}
+
+/// Creates a server of the `starknet` namespace, along with a `dev` one for the same sequencer.
+pub(crate) fn create_starknet_rpcs(
+    config: StarknetConfig,
+) -> (StarknetRpc<KatanaSequencer>, DevRpc<KatanaSequencer>) {
+    let sequencer = create_sequencer(config);
+    let audit_log = create_audit_log();
+
+    (
+        StarknetRpc::new(sequencer.clone(), audit_log.clone()),
+        DevRpc::new(sequencer, audit_log, None),
+    )
+}
+
+/// The path of the account contract of the `katana-core` tests.
+pub(crate) fn test_account_path() -> PathBuf {
+    [
+        env!("CARGO_MANIFEST_DIR"),
+        "../katana-core",
+        TEST_ACCOUNT_CONTRACT_PATH,
+    ]
+    .iter()
+    .collect()
+}
+
+/// A Cairo 1 contract class, which `dev_deployContract` declares along with the deployment.
+pub(crate) fn test_contract_class() -> FlattenedSierraClass {
+    let path: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests/test_data/cairo1_contract.json",
+    ]
+    .iter()
+    .collect();
+
+    serde_json::from_str::<SierraClass>(&fs::read_to_string(path).unwrap())
+        .unwrap()
+        .flatten()
+        .unwrap()
+}
+
+/// The code of a custom RPC error, as returned by the servers for their API errors.
+pub(crate) fn error_code(err: Error) -> Option<i32> {
+    match err {
+        Error::Call(CallError::Custom(error)) => Some(error.code()),
+        _ => None,
+    }
+}
@@ -1,5 +1,10 @@
-use anyhow::{Context, Ok, Result};
+use std::io::Read;
+
+use anyhow::{ensure, Context, Ok, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
 use cairo_lang_starknet::{casm_contract_class::CasmContractClass, contract_class::ContractClass};
+use flate2::read::GzDecoder;
+use serde_json::Value;
 use starknet::core::types::contract::legacy::LegacyContractClass;
 use starknet::core::types::contract::{CompiledClass, SierraClass};
 use starknet::core::types::FieldElement;
@@ -24,3 +29,90 @@ pub fn get_legacy_contract_class_hash(raw_contract_class: &str) -> Result<FieldE
     let legacy_contract_class: LegacyContractClass = serde_json::from_str(raw_contract_class)?;
     Ok(legacy_contract_class.class_hash()?)
 }
+
+/// Converts a legacy contract class in the compressed format of the RPC spec (with a gzipped and
+/// base64 encoded program) to its raw JSON representation. Fails if the decompressed program is
+/// larger than `max_program_size` bytes.
+pub fn decompress_legacy_contract_class(
+    compressed_contract_class: &str,
+    max_program_size: u64,
+) -> Result<String> {
+    let mut contract_class: Value = serde_json::from_str(compressed_contract_class)?;
+
+    let program = contract_class
+        .get("program")
+        .and_then(Value::as_str)
+        .with_context(|| "missing compressed program")?;
+    let program = STANDARD.decode(program)?;
+
+    let mut decompressed_program = Vec::new();
+    GzDecoder::new(program.as_slice())
+        .take(max_program_size + 1)
+        .read_to_end(&mut decompressed_program)?;
+    ensure!(
+        decompressed_program.len() as u64 <= max_program_size,
+        "decompressed program is larger than {max_program_size} bytes"
+    );
+
+    contract_class["program"] = serde_json::from_slice(&decompressed_program)?;
+    if contract_class["abi"].is_null() {
+        contract_class["abi"] = Value::Array(vec![]);
+    }
+
+    Ok(serde_json::to_string(&contract_class)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+    use serde_json::json;
+
+    use super::*;
+
+    fn compressed_contract_class(program: &[u8]) -> String {
+        json!({
+            "program": STANDARD.encode(program),
+            "entry_points_by_type": {},
+            "abi": null,
+        })
+        .to_string()
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_decompress_legacy_contract_class() {
+        let program = br#"{"data":["0x1","0x2"]}"#;
+        let contract_class = compressed_contract_class(&gzip(program));
+
+        let decompressed: Value = serde_json::from_str(
+            &decompress_legacy_contract_class(&contract_class, program.len() as u64).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(decompressed["program"], json!({ "data": ["0x1", "0x2"] }));
+        assert_eq!(decompressed["abi"], json!([]));
+
+        // The program must not be larger than the limit once decompressed.
+        assert!(
+            decompress_legacy_contract_class(&contract_class, program.len() as u64 - 1).is_err()
+        );
+    }
+
+    #[test]
+    fn test_decompress_invalid_legacy_contract_class() {
+        let invalid_base64 = json!({ "program": "not base64!" }).to_string();
+        assert!(decompress_legacy_contract_class(&invalid_base64, 1024).is_err());
+
+        let invalid_gzip = compressed_contract_class(b"not gzip");
+        assert!(decompress_legacy_contract_class(&invalid_gzip, 1024).is_err());
+
+        let missing_program = json!({ "abi": [] }).to_string();
+        assert!(decompress_legacy_contract_class(&missing_program, 1024).is_err());
+    }
+}